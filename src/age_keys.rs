@@ -0,0 +1,127 @@
+use age::{Decryptor, Encryptor, Identity, IdentityFile, Recipient};
+use anyhow::{anyhow, Context, Result};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Encrypts and decrypts `.age` files with X25519 keys.
+///
+/// Files are encrypted to the configured X25519 recipients, or to the recipients of the identity file
+/// when none are configured, and decrypted with the identities of the identity file. Passphrase-encrypted
+/// `.age` files go through [`crate::age_passphrase`].
+#[derive(Clone)]
+pub struct AgeKeys {
+    identity_file: PathBuf,
+    recipients: Vec<String>,
+}
+
+impl AgeKeys {
+    pub fn new(identity_file: &str, recipients: &[String]) -> Self {
+        Self {
+            identity_file: PathBuf::from(identity_file),
+            recipients: recipients.to_vec(),
+        }
+    }
+
+    /// Recipients written into the commit message when re-encrypting, the identity file stands for its own recipients.
+    pub fn recipient_names(&self) -> Vec<String> {
+        if self.recipients.is_empty() {
+            vec![self.identity_file.display().to_string()]
+        } else {
+            self.recipients.clone()
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let recipients: Vec<Box<dyn Recipient + Send>> = if self.recipients.is_empty() {
+            self.read_identity_file()?.to_recipients()?
+        } else {
+            self.recipients
+                .iter()
+                .map(|recipient| {
+                    recipient
+                        .parse::<age::x25519::Recipient>()
+                        .map(|recipient| Box::new(recipient) as Box<dyn Recipient + Send>)
+                        .map_err(|e| anyhow!("Invalid age recipient '{}': {}", recipient, e))
+                })
+                .collect::<Result<_>>()?
+        };
+
+        let encryptor = Encryptor::with_recipients(recipients.iter().map(|recipient| recipient.as_ref() as &dyn Recipient))
+            .context("Failed to encrypt age content")?;
+        let mut ciphertext = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+        writer.write_all(plaintext)?;
+        writer.finish().context("Failed to encrypt age content")?;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let identities = self.read_identity_file()?.into_identities()?;
+        let decryptor = Decryptor::new(ciphertext).context("Failed to decrypt age content")?;
+        let mut reader = decryptor
+            .decrypt(identities.iter().map(|identity| identity.as_ref() as &dyn Identity))
+            .context("Failed to decrypt age content")?;
+
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext).context("Failed to decrypt age content")?;
+        Ok(plaintext)
+    }
+
+    /// Read on every use, so a changed identity file is picked up without a restart.
+    fn read_identity_file(&self) -> Result<IdentityFile<age::NoCallbacks>> {
+        IdentityFile::from_file(self.identity_file.display().to_string())
+            .context(format!("Failed to read the age identity file {:?}", self.identity_file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_identity_file(directory: &TempDir, identity: &age::x25519::Identity) -> String {
+        let path = directory.path().join("keys.txt");
+        fs::write(&path, format!("# created: for a test\n{}\n", identity.to_string().expose_secret())).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn encrypt_to_the_identity_file_without_recipients() {
+        let directory = TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let age_keys = AgeKeys::new(&write_identity_file(&directory, &identity), &[]);
+
+        let ciphertext = age_keys.encrypt(b"password = \"s3cret\"\n").unwrap();
+
+        assert_eq!(age::decrypt(&identity, &ciphertext).unwrap(), b"password = \"s3cret\"\n");
+        assert_eq!(age_keys.decrypt(&ciphertext).unwrap(), b"password = \"s3cret\"\n");
+    }
+
+    #[test]
+    fn encrypt_to_the_configured_recipients() {
+        let directory = TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let other_identity = age::x25519::Identity::generate();
+        let recipients = [identity.to_public().to_string(), other_identity.to_public().to_string()];
+        let age_keys = AgeKeys::new(&write_identity_file(&directory, &identity), &recipients);
+
+        let ciphertext = age_keys.encrypt(b"s3cret").unwrap();
+
+        assert_eq!(age::decrypt(&other_identity, &ciphertext).unwrap(), b"s3cret");
+        assert_eq!(age_keys.decrypt(&ciphertext).unwrap(), b"s3cret");
+        assert_eq!(age_keys.recipient_names(), recipients.to_vec());
+    }
+
+    #[test]
+    fn reject_invalid_recipients_and_foreign_files() {
+        let directory = TempDir::new().unwrap();
+        let identity_file = write_identity_file(&directory, &age::x25519::Identity::generate());
+        let foreign_ciphertext = age::encrypt(&age::x25519::Identity::generate().to_public(), b"s3cret").unwrap();
+
+        assert!(AgeKeys::new(&identity_file, &["age1invalid".to_string()]).encrypt(b"s3cret").is_err());
+        assert!(AgeKeys::new(&identity_file, &[]).decrypt(&foreign_ciphertext).is_err());
+        assert!(AgeKeys::new(&directory.path().join("missing.txt").display().to_string(), &[]).decrypt(&foreign_ciphertext).is_err());
+    }
+}
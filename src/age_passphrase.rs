@@ -0,0 +1,51 @@
+use age::secrecy::SecretString;
+use anyhow::{anyhow, Context, Result};
+
+/// Encrypts `plaintext` with a passphrase, as `age --passphrase` does.
+pub fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let recipient = age::scrypt::Recipient::new(SecretString::from(passphrase.to_string()));
+    age::encrypt(&recipient, plaintext).context("Failed to encrypt age content with the passphrase")
+}
+
+pub fn decrypt(passphrase: &str, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+    age::decrypt(&identity, ciphertext).map_err(|e| anyhow!("Failed to decrypt age content with the passphrase: {}", e))
+}
+
+/// Whether `ciphertext` is an age file encrypted with a passphrase rather than to recipients.
+pub fn is_passphrase_encrypted(ciphertext: &[u8]) -> bool {
+    age::Decryptor::new(ciphertext).is_ok_and(|decryptor| decryptor.is_scrypt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_with_the_same_passphrase() {
+        let ciphertext = encrypt("correct horse", b"password = \"s3cret\"\n").unwrap();
+
+        assert!(is_passphrase_encrypted(&ciphertext));
+        assert_eq!(decrypt("correct horse", &ciphertext).unwrap(), b"password = \"s3cret\"\n");
+    }
+
+    #[test]
+    fn reject_a_wrong_passphrase() {
+        let ciphertext = encrypt("correct horse", b"s3cret").unwrap();
+
+        assert!(decrypt("wrong horse", &ciphertext).is_err());
+    }
+
+    #[test]
+    fn recipient_files_are_not_passphrase_encrypted() {
+        let ciphertext = age::encrypt(&age::x25519::Identity::generate().to_public(), b"s3cret").unwrap();
+
+        assert!(!is_passphrase_encrypted(&ciphertext));
+    }
+
+    #[test]
+    fn other_files_are_not_passphrase_encrypted() {
+        assert!(!is_passphrase_encrypted(b"-----BEGIN PGP MESSAGE-----\n"));
+        assert!(!is_passphrase_encrypted(b""));
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::Result;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::cache::CachedSecretsResult;
+use crate::secret_store::SecretStore;
+use eframe::egui::{Align, CentralPanel, Id, Layout, Panel, TextEdit, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const ATTACH_FILE_TITLE: &str = "Attach File";
+const ATTACHMENT_TITLE: &str = "Attachment";
+const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
+const ATTACH_BUTTON_LABEL: &str = "\u{1f4ce} Attach";
+const SAVE_BUTTON_LABEL: &str = "\u{1f4be} Save";
+const OVERWRITE_BUTTON_LABEL: &str = "\u{26a0} Overwrite";
+const REMOVE_BUTTON_LABEL: &str = "\u{1f5d1} Remove";
+
+/// Dialog to attach a file to a secret, or to save or remove an existing attachment.
+pub struct AttachmentUI {
+    secret_store: Rc<dyn SecretStore>,
+    secret_name: String,
+    /// `None` while attaching a new file.
+    attachment_name: Option<String>,
+    file_path: String,
+    /// Set once saving found a file at `file_path`, the next save replaces it.
+    confirm_overwrite: bool,
+    open_dialog: bool,
+    result_message: Option<String>,
+    error_message: Option<String>,
+}
+
+impl AttachmentUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            secret_name: "".to_string(),
+            attachment_name: None,
+            file_path: "".to_string(),
+            confirm_overwrite: false,
+            open_dialog: false,
+            result_message: None,
+            error_message: None,
+        }
+    }
+
+    pub fn open_attach(&mut self, secret_name: &str) {
+        self.open_with(secret_name, None, "".to_string());
+    }
+
+    pub fn open_attachment(&mut self, secret_name: &str, attachment_name: &str) {
+        let home_directory = std::env::var("HOME").unwrap_or(".".to_string());
+        let default_path = Path::new(&home_directory).join(attachment_name).to_string_lossy().to_string();
+        self.open_with(secret_name, Some(attachment_name.to_string()), default_path);
+    }
+
+    fn open_with(&mut self, secret_name: &str, attachment_name: Option<String>, file_path: String) {
+        self.secret_name = secret_name.to_string();
+        self.attachment_name = attachment_name;
+        self.file_path = file_path;
+        self.confirm_overwrite = false;
+        self.result_message = None;
+        self.error_message = None;
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.secret_name = "".to_string();
+        self.attachment_name = None;
+        self.file_path = "".to_string();
+        self.confirm_overwrite = false;
+        self.result_message = None;
+        self.error_message = None;
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let title = match &self.attachment_name {
+                Some(attachment_name) => format!("{}: {}", ATTACHMENT_TITLE, attachment_name),
+                None => format!("{}: {}", ATTACH_FILE_TITLE, self.secret_name),
+            };
+            let attachment_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(480.0, 140.0))
+                .with_title(title)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("attachment_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, attachment_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("attachment_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CANCEL_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if self.attachment_name.is_some() {
+                                if ui.button(REMOVE_BUTTON_LABEL).clicked() {
+                                    self.handle_remove(ui);
+                                }
+                                let save_button_label = if self.confirm_overwrite { OVERWRITE_BUTTON_LABEL } else { SAVE_BUTTON_LABEL };
+                                if ui.button(save_button_label).clicked() {
+                                    self.handle_save();
+                                }
+                            } else if ui.button(ATTACH_BUTTON_LABEL).clicked() {
+                                self.handle_attach(ui);
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    let label = if self.attachment_name.is_some() { "Save the decrypted file to:" } else { "Path of the file to encrypt and attach:" };
+                    ui.label(label);
+                    if ui.add(TextEdit::singleline(&mut self.file_path).desired_width(f32::INFINITY)).changed() {
+                        self.confirm_overwrite = false;
+                    }
+                    if let Some(result) = &self.result_message {
+                        ui.label(result);
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+                });
+            });
+        }
+    }
+
+    fn handle_attach(&mut self, ui: &mut Ui) {
+        let file_path = Path::new(&self.file_path);
+        let attachment_name = file_path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+
+        let attach_result = fs::read(file_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| self.secret_store.add_attachment(&self.secret_name, &attachment_name, &content));
+        match attach_result {
+            Ok(_) => {
+                Self::clear_ui_cache(ui, &self.secret_name);
+                self.close();
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to attach file: {}", e));
+            }
+        }
+    }
+
+    fn handle_save(&mut self) {
+        let overwrite = self.confirm_overwrite;
+        if !overwrite && Path::new(&self.file_path).exists() {
+            self.confirm_overwrite = true;
+            self.result_message = None;
+            self.error_message = Some(format!("{} already exists, press \"Overwrite\" to replace it.", self.file_path));
+            return;
+        }
+
+        let attachment_name = self.attachment_name.clone().unwrap_or_default();
+        let save_result = self
+            .secret_store
+            .load_attachment(&self.secret_name, &attachment_name)
+            .and_then(|content| Self::write_private_file(Path::new(&self.file_path), &content, overwrite));
+        self.confirm_overwrite = false;
+        match save_result {
+            Ok(_) => {
+                self.error_message = None;
+                self.result_message = Some(format!("Saved {} to {}", attachment_name, self.file_path));
+            }
+            Err(e) => {
+                self.result_message = None;
+                self.error_message = Some(format!("Unable to save attachment: {}", e));
+            }
+        }
+    }
+
+    /// Writes the decrypted content readable by the owner only. Without `overwrite`, an existing file is
+    /// never replaced, even one created after the check in `handle_save`.
+    fn write_private_file(path: &Path, content: &[u8], overwrite: bool) -> Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if overwrite {
+            options.create(true).truncate(true);
+        } else {
+            options.create_new(true);
+        }
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(path)?;
+        // The mode only applies to new files, an overwritten file keeps its permissions otherwise.
+        #[cfg(unix)]
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(content)?;
+        Ok(())
+    }
+
+    fn handle_remove(&mut self, ui: &mut Ui) {
+        let attachment_name = self.attachment_name.clone().unwrap_or_default();
+        match self.secret_store.remove_attachment(&self.secret_name, &attachment_name) {
+            Ok(_) => {
+                Self::clear_ui_cache(ui, &self.secret_name);
+                self.close();
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to remove attachment: {}", e));
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui, secret_name: &str) {
+        let secret_cache_id = Id::new(secret_name).with("cache");
+        ui.memory_mut(|m| m.data.remove::<CachedSecretsResult>(secret_cache_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::{Secret, SecretFormat};
+    use crate::secret_store::MemorySecretStore;
+    use tempfile::TempDir;
+
+    fn attachment_ui() -> (AttachmentUI, TempDir) {
+        let secret_store: Rc<dyn SecretStore> = Rc::new(MemorySecretStore::new());
+        secret_store.update_secret(None, "bank", &Secret::new(Default::default(), SecretFormat::Toml)).unwrap();
+        secret_store.add_attachment("bank", "card.txt", b"decrypted").unwrap();
+
+        let directory = TempDir::new().unwrap();
+
+        let mut attachment_ui = AttachmentUI::new(&secret_store);
+        attachment_ui.open_attachment("bank", "card.txt");
+        attachment_ui.file_path = directory.path().join("card.txt").to_string_lossy().to_string();
+        (attachment_ui, directory)
+    }
+
+    #[test]
+    fn save_creates_a_file_readable_by_the_owner_only() {
+        let (mut attachment_ui, directory) = attachment_ui();
+
+        attachment_ui.handle_save();
+
+        assert!(attachment_ui.error_message.is_none());
+        assert_eq!(fs::read(directory.path().join("card.txt")).unwrap(), b"decrypted");
+        #[cfg(unix)]
+        assert_eq!(fs::metadata(directory.path().join("card.txt")).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn save_asks_before_overwriting_an_existing_file() {
+        let (mut attachment_ui, directory) = attachment_ui();
+        fs::write(directory.path().join("card.txt"), b"existing").unwrap();
+
+        attachment_ui.handle_save();
+
+        assert!(attachment_ui.confirm_overwrite);
+        assert!(attachment_ui.error_message.is_some());
+        assert_eq!(fs::read(directory.path().join("card.txt")).unwrap(), b"existing");
+
+        attachment_ui.handle_save();
+
+        assert!(!attachment_ui.confirm_overwrite);
+        assert!(attachment_ui.error_message.is_none());
+        assert_eq!(fs::read(directory.path().join("card.txt")).unwrap(), b"decrypted");
+        #[cfg(unix)]
+        assert_eq!(fs::metadata(directory.path().join("card.txt")).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,224 @@
+use std::rc::Rc;
+
+use crate::breached_passwords::BreachedPasswords;
+use crate::password_strength::PasswordFieldNames;
+use crate::secret_store::SecretStore;
+use eframe::egui::{Align, Button, CentralPanel, Id, Layout, Panel, ProgressBar, RichText, ScrollArea, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const BREACH_CHECK_TITLE: &str = "Breach Check";
+const CLOSE_BUTTON_LABEL: &str = "\u{274c} Close";
+const CHECK_AGAIN_BUTTON_LABEL: &str = "\u{1f504} Check Again";
+
+/// A password field whose value appears in the breached password hashes.
+pub struct BreachedField {
+    pub secret_name: String,
+    pub field_path: String,
+    pub times_breached: u64,
+}
+
+/// Looks up the password fields of one secret, or of every secret, in a local copy of the breached password hashes.
+pub struct BreachCheckUI {
+    secret_store: Rc<dyn SecretStore>,
+    breached_passwords: Option<BreachedPasswords>,
+    password_field_names: PasswordFieldNames,
+    open_dialog: bool,
+    /// The secret checked on its own, or `None` for the whole vault.
+    secret_name: Option<String>,
+    is_running: bool,
+    pending_secrets: Vec<String>,
+    total_secrets: usize,
+    checked_fields: usize,
+    breached_fields: Vec<BreachedField>,
+    failures: Vec<(String, String)>,
+    error_message: Option<String>,
+}
+
+impl BreachCheckUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            breached_passwords: None,
+            password_field_names: PasswordFieldNames::default(),
+            open_dialog: false,
+            secret_name: None,
+            is_running: false,
+            pending_secrets: Vec::new(),
+            total_secrets: 0,
+            checked_fields: 0,
+            breached_fields: Vec::new(),
+            failures: Vec::new(),
+            error_message: None,
+        }
+    }
+
+    pub fn with_breached_passwords(mut self, breached_passwords: Option<BreachedPasswords>) -> Self {
+        self.breached_passwords = breached_passwords;
+        self
+    }
+
+    /// Keys of the fields whose values are looked up.
+    pub fn with_password_field_names(mut self, password_field_names: PasswordFieldNames) -> Self {
+        self.password_field_names = password_field_names;
+        self
+    }
+
+    /// Only offered once the breached password hashes are configured.
+    pub fn is_available(&self) -> bool {
+        self.breached_passwords.is_some()
+    }
+
+    pub fn open_for_secret(&mut self, secret_name: &str) {
+        self.secret_name = Some(secret_name.to_string());
+        self.start();
+        self.open_dialog = true;
+    }
+
+    pub fn open_for_vault(&mut self) {
+        self.secret_name = None;
+        self.start();
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.reset();
+        self.secret_name = None;
+        self.open_dialog = false;
+    }
+
+    fn reset(&mut self) {
+        self.is_running = false;
+        self.pending_secrets = Vec::new();
+        self.total_secrets = 0;
+        self.checked_fields = 0;
+        self.breached_fields = Vec::new();
+        self.failures = Vec::new();
+        self.error_message = None;
+    }
+
+    fn start(&mut self) {
+        self.reset();
+        let secret_names = match &self.secret_name {
+            Some(secret_name) => Ok(vec![secret_name.clone()]),
+            None => self.secret_store.load_secret_names(),
+        };
+        match secret_names {
+            Ok(mut secret_names) => {
+                secret_names.reverse();
+                self.total_secrets = secret_names.len();
+                self.pending_secrets = secret_names;
+                self.is_running = true;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to list secrets: {}", e));
+            }
+        }
+    }
+
+    /// Checks one secret per frame so the progress bar keeps moving.
+    fn check_next_secret(&mut self, ui: &Ui) {
+        let (Some(secret_name), Some(breached_passwords)) = (self.pending_secrets.pop(), &self.breached_passwords) else {
+            self.is_running = false;
+            return;
+        };
+
+        let password_fields = match self.secret_store.load_secrets(&secret_name) {
+            Ok(secret) => self.password_field_names.password_fields(&secret.fields),
+            Err(e) => {
+                log::error!("Failed to load secret '{}' for the breach check: {:#?}", secret_name, e);
+                self.failures.push((secret_name, format!("{:#}", e)));
+                ui.ctx().request_repaint();
+                return;
+            }
+        };
+
+        for (field_path, password) in password_fields {
+            match breached_passwords.times_breached(&password) {
+                Ok(Some(times_breached)) => self.breached_fields.push(BreachedField {
+                    secret_name: secret_name.clone(),
+                    field_path,
+                    times_breached,
+                }),
+                Ok(None) => {}
+                Err(e) => {
+                    log::error!("Failed to look up field '{}' of secret '{}': {:#?}", field_path, secret_name, e);
+                    self.failures.push((format!("{} / {}", secret_name, field_path), format!("{:#}", e)));
+                    continue;
+                }
+            }
+            self.checked_fields += 1;
+        }
+        ui.ctx().request_repaint();
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let title = match &self.secret_name {
+                Some(secret_name) => format!("{}: {}", BREACH_CHECK_TITLE, secret_name),
+                None => BREACH_CHECK_TITLE.to_string(),
+            };
+            let breach_check_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(520.0, 360.0))
+                .with_title(title)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("breach_check_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, breach_check_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                if self.is_running {
+                    self.check_next_secret(ui);
+                }
+
+                Panel::bottom(Id::new("breach_check_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CLOSE_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.add_enabled(!self.is_running, Button::new(CHECK_AGAIN_BUTTON_LABEL)).clicked() {
+                                self.start();
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    ui.label("Password fields are hashed and looked up in the local copy of the breached password hashes, nothing is sent over the network.");
+                    ui.add_space(6.0);
+
+                    if self.total_secrets > 1 {
+                        let checked_secrets = self.total_secrets - self.pending_secrets.len();
+                        ui.add(ProgressBar::new(checked_secrets as f32 / self.total_secrets as f32).text(format!("{} / {}", checked_secrets, self.total_secrets)));
+                    }
+                    if !self.is_running && self.error_message.is_none() {
+                        let summary = match self.breached_fields.len() {
+                            0 => format!("\u{2714} None of the {} password field(s) appears in known breaches.", self.checked_fields),
+                            breached => format!("\u{26a0} {} of {} password field(s) appear in known breaches, change them.", breached, self.checked_fields),
+                        };
+                        ui.label(summary);
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+
+                    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                        self.breached_fields.iter().for_each(|breached_field| {
+                            ui.horizontal(|ui| {
+                                ui.label(RichText::new(format!("{} / {}", breached_field.secret_name, breached_field.field_path)).strong());
+                                ui.colored_label(ui.style().visuals.error_fg_color, format!("seen {} time(s) in breaches", breached_field.times_breached));
+                            });
+                        });
+                        self.failures.iter().for_each(|(checked, error)| {
+                            ui.colored_label(ui.style().visuals.error_fg_color, format!("{}: {}", checked, error));
+                        });
+                    });
+                });
+            });
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{anyhow, Context, Result};
+use sha1::{Digest, Sha1};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Length of the hash prefix that names a range file, as in the k-anonymity API.
+const RANGE_PREFIX_LENGTH: usize = 5;
+
+/// A local copy of the Have I Been Pwned SHA-1 password hashes, nothing is sent over the network.
+#[derive(Clone, Debug)]
+pub enum BreachedPasswords {
+    /// One file per 5 character hash prefix, e.g. `21BD1.txt`, with `SUFFIX:COUNT` lines, as the range API
+    /// returns them and `haveibeenpwned-downloader` writes them.
+    RangeDirectory(PathBuf),
+    /// One file with a `HASH:COUNT` line per hash, sorted by hash, searched without reading it whole.
+    SortedFile(PathBuf),
+}
+
+impl BreachedPasswords {
+    pub fn open(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path).context(format!("Cannot open the breached password hashes at {:?}", path))?;
+        if metadata.is_dir() {
+            Ok(Self::RangeDirectory(path.to_path_buf()))
+        } else {
+            Ok(Self::SortedFile(path.to_path_buf()))
+        }
+    }
+
+    /// How often the password appears in known breaches, `None` when it does not.
+    pub fn times_breached(&self, password: &str) -> Result<Option<u64>> {
+        let hash: String = Sha1::digest(password.as_bytes()).iter().map(|byte| format!("{:02X}", byte)).collect();
+        match self {
+            Self::RangeDirectory(directory) => Self::search_range(directory, &hash),
+            Self::SortedFile(file) => Self::search_sorted_file(file, &hash),
+        }
+    }
+
+    fn search_range(directory: &Path, hash: &str) -> Result<Option<u64>> {
+        let (prefix, suffix) = hash.split_at(RANGE_PREFIX_LENGTH);
+        let range_file = [format!("{}.txt", prefix), prefix.to_string(), format!("{}.txt", prefix.to_lowercase())]
+            .into_iter()
+            .map(|file_name| directory.join(file_name))
+            .find(|range_file| range_file.is_file())
+            .ok_or_else(|| anyhow!("The range file for prefix {} is missing in {:?}, is the download complete?", prefix, directory))?;
+
+        let content = fs::read_to_string(&range_file).context(format!("Failed to read {:?}", range_file))?;
+        content
+            .lines()
+            .filter_map(Self::parse_line)
+            .find(|(line_suffix, _)| line_suffix.eq_ignore_ascii_case(suffix))
+            .map(|(_, count)| count)
+            .transpose()
+    }
+
+    /// Binary search over byte offsets: `low` is always the start of a line, and a line equal to `hash` can
+    /// only start in `low..high`.
+    fn search_sorted_file(path: &Path, hash: &str) -> Result<Option<u64>> {
+        let mut reader = BufReader::new(File::open(path).context(format!("Failed to open {:?}", path))?);
+        let (mut low, mut high) = (0u64, reader.get_ref().metadata()?.len());
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let Some((line_start, next_line_start, line)) = Self::read_line_from(&mut reader, middle)? else {
+                high = middle;
+                continue;
+            };
+            if line_start >= high {
+                high = middle;
+                continue;
+            }
+
+            let (line_hash, count) = Self::parse_line(&line).ok_or_else(|| anyhow!("{:?} is not a list of HASH:COUNT lines", path))?;
+            match line_hash.to_ascii_uppercase().as_str().cmp(hash) {
+                std::cmp::Ordering::Less => low = next_line_start,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return count.map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    /// The first line starting at or after `offset` with its start and the start of the line after it.
+    fn read_line_from(reader: &mut BufReader<File>, offset: u64) -> Result<Option<(u64, u64, String)>> {
+        let line_start = if offset == 0 {
+            reader.seek(SeekFrom::Start(0))?;
+            0
+        } else {
+            reader.seek(SeekFrom::Start(offset - 1))?;
+            let mut rest_of_previous_line = Vec::new();
+            offset - 1 + reader.read_until(b'\n', &mut rest_of_previous_line)? as u64
+        };
+
+        let mut line = String::new();
+        let line_length = reader.read_line(&mut line)? as u64;
+        if line_length == 0 {
+            return Ok(None);
+        }
+        Ok(Some((line_start, line_start + line_length, line)))
+    }
+
+    fn parse_line(line: &str) -> Option<(&str, Result<u64>)> {
+        let (hash, count) = line.trim_end().split_once(':')?;
+        Some((hash, count.parse::<u64>().map_err(|e| anyhow!("Invalid count '{}': {}", count, e))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PASSWORDS: [&str; 7] = ["password", "123456", "letmein", "qwerty", "dragon", "monkey", "sunshine"];
+
+    fn sha1_hex(password: &str) -> String {
+        Sha1::digest(password.as_bytes()).iter().map(|byte| format!("{:02X}", byte)).collect()
+    }
+
+    /// `HASH:COUNT` lines sorted by hash, the count of each password being its position in `PASSWORDS` plus one.
+    fn sorted_lines() -> Vec<(String, u64)> {
+        let mut lines: Vec<(String, u64)> = PASSWORDS.iter().enumerate().map(|(index, password)| (sha1_hex(password), index as u64 + 1)).collect();
+        lines.sort();
+        lines
+    }
+
+    fn write_sorted_file(line_ending: &str, trailing_line_ending: bool) -> (BreachedPasswords, TempDir) {
+        let directory = TempDir::new().unwrap();
+        let mut content = sorted_lines().iter().map(|(hash, count)| format!("{}:{}", hash, count)).collect::<Vec<String>>().join(line_ending);
+        if trailing_line_ending {
+            content.push_str(line_ending);
+        }
+        let path = directory.path().join("pwned-passwords-sha1-ordered-by-hash.txt");
+        fs::write(&path, content).unwrap();
+        (BreachedPasswords::open(&path).unwrap(), directory)
+    }
+
+    fn password_with_hash(hash: &str) -> &'static str {
+        PASSWORDS.iter().find(|password| sha1_hex(password) == hash).unwrap()
+    }
+
+    #[test]
+    fn sorted_file_finds_first_and_last_line() {
+        let (breached_passwords, _directory) = write_sorted_file("\n", true);
+        let lines = sorted_lines();
+        let (first_hash, first_count) = lines.first().unwrap();
+        let (last_hash, last_count) = lines.last().unwrap();
+
+        assert_eq!(breached_passwords.times_breached(password_with_hash(first_hash)).unwrap(), Some(*first_count));
+        assert_eq!(breached_passwords.times_breached(password_with_hash(last_hash)).unwrap(), Some(*last_count));
+    }
+
+    #[test]
+    fn sorted_file_finds_every_line() {
+        let (breached_passwords, _directory) = write_sorted_file("\r\n", true);
+
+        for (index, password) in PASSWORDS.iter().enumerate() {
+            assert_eq!(breached_passwords.times_breached(password).unwrap(), Some(index as u64 + 1), "{}", password);
+        }
+    }
+
+    #[test]
+    fn sorted_file_misses_unknown_hashes() {
+        let (breached_passwords, _directory) = write_sorted_file("\n", true);
+
+        assert_eq!(breached_passwords.times_breached("correct horse battery staple").unwrap(), None);
+        assert_eq!(breached_passwords.times_breached("").unwrap(), None);
+    }
+
+    #[test]
+    fn sorted_file_without_trailing_newline() {
+        let (breached_passwords, _directory) = write_sorted_file("\n", false);
+        let (last_hash, last_count) = sorted_lines().pop().unwrap();
+
+        assert_eq!(breached_passwords.times_breached(password_with_hash(&last_hash)).unwrap(), Some(last_count));
+        assert_eq!(breached_passwords.times_breached("correct horse battery staple").unwrap(), None);
+    }
+
+    #[test]
+    fn sorted_file_with_a_single_line() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("hashes.txt");
+        fs::write(&path, format!("{}:42", sha1_hex("password"))).unwrap();
+        let breached_passwords = BreachedPasswords::open(&path).unwrap();
+
+        assert_eq!(breached_passwords.times_breached("password").unwrap(), Some(42));
+        assert_eq!(breached_passwords.times_breached("letmein").unwrap(), None);
+    }
+
+    #[test]
+    fn range_directory_looks_up_the_prefix_file() {
+        let directory = TempDir::new().unwrap();
+        let password_hash = sha1_hex("password");
+        let (prefix, suffix) = password_hash.split_at(RANGE_PREFIX_LENGTH);
+        fs::write(directory.path().join(format!("{}.txt", prefix)), format!("0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n{}:9659365\r\n", suffix)).unwrap();
+        let letmein_hash = sha1_hex("letmein");
+        let (prefix, suffix) = letmein_hash.split_at(RANGE_PREFIX_LENGTH);
+        fs::write(directory.path().join(prefix.to_lowercase() + ".txt"), format!("{}:3\n", suffix.to_lowercase())).unwrap();
+        let breached_passwords = BreachedPasswords::open(directory.path()).unwrap();
+
+        assert!(matches!(breached_passwords, BreachedPasswords::RangeDirectory(_)));
+        assert_eq!(breached_passwords.times_breached("password").unwrap(), Some(9659365));
+        assert_eq!(breached_passwords.times_breached("letmein").unwrap(), Some(3));
+    }
+
+    #[test]
+    fn range_directory_misses_unknown_suffixes_and_reports_missing_files() {
+        let directory = TempDir::new().unwrap();
+        let password_hash = sha1_hex("password");
+        let (prefix, _) = password_hash.split_at(RANGE_PREFIX_LENGTH);
+        fs::write(directory.path().join(prefix), "0018A45C4D1DEF81644B54AB7F969B88D65:1\n").unwrap();
+        let breached_passwords = BreachedPasswords::open(directory.path()).unwrap();
+
+        assert_eq!(breached_passwords.times_breached("password").unwrap(), None);
+        assert!(breached_passwords.times_breached("correct horse battery staple").is_err());
+    }
+
+    #[test]
+    fn open_rejects_missing_paths() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(BreachedPasswords::open(&directory.path().join("missing.txt")).is_err());
+    }
+}
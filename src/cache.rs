@@ -1,2 +1,26 @@
-/// Type alias for cached secret data: the list of key/value pairs, or an error string.
-pub type CachedSecretsResult = Result<Vec<(String, String)>, String>;
+use crate::secret::SignatureStatus;
+use eframe::egui::{Id, util::IdTypeMap};
+use toml::Value;
+
+/// Decrypted fields of a secret in display order, together with the names of its attachments and its signature check.
+#[derive(Clone)]
+pub struct CachedSecret {
+    pub fields: Vec<(String, Value)>,
+    pub attachments: Vec<String>,
+    pub signature: Option<SignatureStatus>,
+}
+
+/// Type alias for cached secret data: the displayed secret, or an error string.
+pub type CachedSecretsResult = Result<CachedSecret, String>;
+
+/// Where the last signature check of a secret is kept, apart from its decrypted fields so that it survives collapsing
+/// the secret. `None` when the secret could not be checked.
+pub fn signature_id(secret_name: &str) -> Id {
+    Id::new(secret_name).with("signature")
+}
+
+/// Forgets the decrypted secret and its last signature check, for when the secret changed.
+pub fn forget_secret(data: &mut IdTypeMap, secret_name: &str) {
+    data.remove::<CachedSecretsResult>(Id::new(secret_name).with("cache"));
+    data.remove::<Option<SignatureStatus>>(signature_id(secret_name));
+}
@@ -0,0 +1,130 @@
+use std::rc::Rc;
+
+use crate::cache::forget_secret;
+use crate::secret_store::SecretStore;
+use eframe::egui::{Align, Button, CentralPanel, ComboBox, Id, Layout, Panel, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const CONVERT_SECRET_TITLE: &str = "Convert Secret";
+const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
+const CONVERT_BUTTON_LABEL: &str = "\u{1f501} Convert";
+
+pub struct ConvertSecretUI {
+    secret_store: Rc<dyn SecretStore>,
+    secret_name: String,
+    current_format: String,
+    target_format: String,
+    open_dialog: bool,
+    error_message: Option<String>,
+}
+
+impl ConvertSecretUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            secret_name: String::new(),
+            current_format: String::new(),
+            target_format: String::new(),
+            open_dialog: false,
+            error_message: None,
+        }
+    }
+
+    /// Converting only makes sense when the storage backend offers more than one encryption format.
+    pub fn is_available(&self) -> bool {
+        self.secret_store.encryption_formats().len() > 1
+    }
+
+    pub fn open(&mut self, secret_name: &str) {
+        self.secret_name = secret_name.to_string();
+        self.error_message = None;
+        match self.secret_store.encryption_format(secret_name) {
+            Ok(current_format) => {
+                self.target_format = self.other_formats(&current_format).into_iter().next().unwrap_or_default();
+                self.current_format = current_format;
+            }
+            Err(e) => {
+                self.current_format = String::new();
+                self.target_format = String::new();
+                self.error_message = Some(format!("Unable to read the format of the secret: {}", e));
+            }
+        }
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.secret_name = String::new();
+        self.current_format = String::new();
+        self.target_format = String::new();
+        self.error_message = None;
+    }
+
+    fn other_formats(&self, current_format: &str) -> Vec<String> {
+        self.secret_store.encryption_formats().into_iter().filter(|format| format != current_format).collect()
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let convert_secret_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(450.0, 140.0))
+                .with_title(format!("{}: {}", CONVERT_SECRET_TITLE, self.secret_name))
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("convert_secret_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, convert_secret_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("convert_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CANCEL_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.add_enabled(!self.target_format.is_empty(), Button::new(CONVERT_BUTTON_LABEL)).clicked() {
+                                self.handle_convert(ui);
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    if !self.current_format.is_empty() {
+                        ui.label(format!("The secret and its attachments are stored as {}.", self.current_format));
+                        ui.horizontal(|ui| {
+                            ui.label("Convert to:");
+                            ComboBox::from_id_salt("convert_target_format").selected_text(self.target_format.as_str()).show_ui(ui, |ui| {
+                                for format in self.other_formats(&self.current_format) {
+                                    ui.selectable_value(&mut self.target_format, format.clone(), format);
+                                }
+                            });
+                        });
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+                });
+            });
+        }
+    }
+
+    fn handle_convert(&mut self, ui: &mut Ui) {
+        match self.secret_store.convert_secret(&self.secret_name, &self.target_format) {
+            Ok(_) => {
+                Self::clear_ui_cache(ui, &self.secret_name);
+                self.close();
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to convert secret: {:#}", e));
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui, secret_name: &str) {
+        ui.memory_mut(|m| forget_secret(&mut m.data, secret_name));
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver};
+
+use super::{CredentialsProvider, SecretFileFormat};
+use crate::secret_store::ATTACHMENTS_SUFFIX;
+
+/// Watches the secrets directory for files created, changed or removed by anyone, including Rustillium itself.
+pub(super) struct DirectoryWatcher {
+    _watcher: RecommendedWatcher,
+    /// Canonical secrets directory the reported paths start with.
+    root: PathBuf,
+    changed_paths: Receiver<PathBuf>,
+}
+
+impl CredentialsProvider {
+    /// `on_change` runs on the watcher's thread after every change outside of `.git`.
+    pub(super) fn start_watching(&self, on_change: Box<dyn Fn() + Send>) -> Result<()> {
+        let root = fs::canonicalize(&self.path).context(format!("Failed to watch {:?}", self.path))?;
+        let git_directory = root.join(".git");
+        let (sender, changed_paths) = channel();
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            // Reading a secret to decrypt it must not count as a change.
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)) {
+                return;
+            }
+
+            let changed_paths: Vec<PathBuf> = event.paths.into_iter().filter(|path| !path.starts_with(&git_directory)).collect();
+            if !changed_paths.is_empty() {
+                changed_paths.into_iter().for_each(|path| {
+                    let _ = sender.send(path);
+                });
+                on_change();
+            }
+        })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        self.watcher.replace(Some(Rc::new(DirectoryWatcher {
+            _watcher: watcher,
+            root,
+            changed_paths,
+        })));
+        Ok(())
+    }
+
+    /// Names of the secrets whose files or attachments changed since the last call, or `None` when nothing changed.
+    pub(super) fn take_changed_secret_names(&self) -> Option<Vec<String>> {
+        let watcher = self.watcher.borrow();
+        let watcher = watcher.as_ref()?;
+        let changed_paths: Vec<PathBuf> = watcher.changed_paths.try_iter().collect();
+        if changed_paths.is_empty() {
+            return None;
+        }
+
+        let mut secret_names: Vec<String> = changed_paths
+            .iter()
+            .filter_map(|path| path.strip_prefix(&watcher.root).ok())
+            .filter_map(Self::secret_name_of_changed_path)
+            .collect();
+        secret_names.sort();
+        secret_names.dedup();
+        Some(secret_names)
+    }
+
+    /// Maps a secret file or a file in its attachments folder to the secret name, anything else to `None`.
+    fn secret_name_of_changed_path(relative_path: &Path) -> Option<String> {
+        let segments: Vec<&str> = relative_path.components().map(|component| component.as_os_str().to_str()).collect::<Option<_>>()?;
+        if segments.iter().any(|segment| segment.starts_with('.')) {
+            return None;
+        }
+
+        let (file_name, parent_segments) = segments.split_last()?;
+        let attachments_folder = segments.iter().position(|segment| segment.ends_with(ATTACHMENTS_SUFFIX));
+        let secret_segment = match attachments_folder {
+            Some(index) => segments[index].strip_suffix(ATTACHMENTS_SUFFIX)?,
+            None => {
+                SecretFileFormat::of(relative_path).ok()?;
+                Path::new(file_name).file_stem()?.to_str()?
+            }
+        };
+        let folder_segments = &parent_segments[..attachments_folder.unwrap_or(parent_segments.len())];
+        Some(folder_segments.iter().copied().chain([secret_segment]).collect::<Vec<&str>>().join("/"))
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Context, Result};
+use git2::{Commit, Oid, Signature, Tree};
+use gpgme::{Context as GpgmeContext, Protocol};
+
+use super::{CredentialsProvider, COMMITTER_EMAIL, COMMITTER_NAME, GPG_ID_FILE_NAME};
+use crate::secret::SignatureStatus;
+
+/// Author recorded on commits, so changes to a shared vault can be told apart.
+#[derive(Clone, Debug, Default)]
+pub struct CommitAuthor {
+    /// Falls back to `user.name` from the git configuration of the secrets repository.
+    pub name: Option<String>,
+    /// Falls back to `user.email` from the git configuration of the secrets repository.
+    pub email: Option<String>,
+}
+
+impl CredentialsProvider {
+    /// Creates a commit on the current branch. The author is the configured user, the committer is always
+    /// Rustillium so its own commits can be recognized. Commits are signed when commit signing is enabled.
+    pub(super) fn write_commit(&self, message: &str, tree: &Tree, parents: &[&Commit]) -> Result<Oid> {
+        let author = self.author_signature()?;
+        let committer = Self::committer_signature()?;
+        if !self.sign_commits {
+            return Ok(self.repository.commit(Some("HEAD"), &author, &committer, message, tree, parents)?);
+        }
+
+        let commit_buffer = self.repository.commit_create_buffer(&author, &committer, message, tree, parents)?;
+        let commit_content = commit_buffer.as_str().ok_or_else(|| anyhow!("Commit content is not valid UTF-8"))?;
+        let signature = self.sign_commit_content(commit_content.as_bytes())?;
+        let commit_id = self.repository.commit_signed(commit_content, &signature, None)?;
+
+        let branch_reference = format!("refs/heads/{}", self.current_branch_name()?);
+        self.repository.reference(&branch_reference, commit_id, true, &format!("commit: {}", message.lines().next().unwrap_or_default()))?;
+        Ok(commit_id)
+    }
+
+    pub(super) fn committer_signature() -> Result<Signature<'static>> {
+        Ok(Signature::now(COMMITTER_NAME, COMMITTER_EMAIL)?)
+    }
+
+    fn author_signature(&self) -> Result<Signature<'static>> {
+        let git_config = self.repository.config().ok();
+        let from_git_config = |key: &str| git_config.as_ref().and_then(|git_config| git_config.get_string(key).ok());
+
+        let name = self.commit_author.name.clone().or_else(|| from_git_config("user.name")).unwrap_or(COMMITTER_NAME.to_string());
+        let email = self.commit_author.email.clone().or_else(|| from_git_config("user.email")).unwrap_or(COMMITTER_EMAIL.to_string());
+        Ok(Signature::now(&name, &email)?)
+    }
+
+    /// Signs with the key secrets are signed with: the configured signing key, or the first recipient of the vault
+    /// with a secret key, taking the `.gpg-id` file of the secrets directory into account. Asks for its passphrase
+    /// through pinentry.
+    fn sign_commit_content(&self, commit_content: &[u8]) -> Result<String> {
+        let mut context = GpgmeContext::from_protocol(Protocol::OpenPgp)?;
+        context.set_armor(true);
+        let recipients = if self.signing_key.is_some() { Vec::new() } else { self.resolve_recipients(&self.path.join(GPG_ID_FILE_NAME))? };
+        let signing_key = self
+            .find_signing_key(&mut context, &recipients)?
+            .ok_or_else(|| anyhow!("Commit signing is enabled but no secret key was found, set 'signing_key' in the configuration."))?;
+        context.add_signer(&signing_key)?;
+
+        let mut signature = Vec::new();
+        context.sign_detached(commit_content, &mut signature).context("Failed to sign the commit")?;
+        Ok(String::from_utf8(signature)?)
+    }
+
+    /// Checks the GPG signature of a commit against the trusted signers.
+    pub(super) fn commit_signature_status(&self, commit: &Commit) -> SignatureStatus {
+        let Ok((signature, signed_content)) = self.repository.extract_signature(&commit.id(), None) else {
+            return SignatureStatus::Unsigned;
+        };
+
+        let verification = GpgmeContext::from_protocol(Protocol::OpenPgp)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut context| Ok((context.verify_detached(&*signature, &*signed_content)?, context)));
+
+        match verification {
+            Ok((verification, mut context)) => Self::to_signature_status(&mut context, &verification, &self.trusted_signers),
+            Err(e) => {
+                log::warn!("Failed to verify the signature of commit {}: {:#?}", commit.id(), e);
+                SignatureStatus::Bad
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Result};
+use git2::build::CheckoutBuilder;
+use git2::{IndexConflict, IndexEntry, IndexTime, Oid};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+use super::{CredentialsProvider, SecretFileFormat};
+use crate::secret::Secret;
+use crate::secret_merge::{merge_fields, resolve_conflict, FieldConflict};
+use crate::secret_store::{MergeConflict, MergeSide, ATTACHMENTS_SUFFIX};
+
+/// Git mode of a regular, non-executable file.
+const FILE_MODE: u32 = 0o100644;
+
+/// A merge of diverged histories waiting for its conflicts to be resolved.
+#[derive(Clone)]
+pub(super) struct PendingMerge {
+    local_oid: Oid,
+    remote_oid: Oid,
+    remote_name: String,
+    files: Vec<MergedFile>,
+}
+
+/// A file git could not merge, with paths relative to the secrets directory.
+#[derive(Clone)]
+enum MergedFile {
+    /// A secret changed on both sides, merged field by field.
+    Secret { path: PathBuf, secret: Secret, conflicts: Vec<FieldConflict> },
+    /// Anything that can only be kept from one side, e.g. an attachment or a secret deleted on one side.
+    Whole { path: PathBuf, ours: Option<Vec<u8>>, theirs: Option<Vec<u8>> },
+}
+
+impl MergedFile {
+    fn path(&self) -> &Path {
+        match self {
+            MergedFile::Secret { path, .. } | MergedFile::Whole { path, .. } => path,
+        }
+    }
+}
+
+impl PendingMerge {
+    /// Conflicts in the order [`CredentialsProvider::complete_merge`] expects their resolutions.
+    pub(super) fn conflicts(&self) -> Vec<MergeConflict> {
+        self.files
+            .iter()
+            .flat_map(|file| {
+                let name = file.path().with_extension("").to_string_lossy().to_string();
+                match file {
+                    MergedFile::Secret { conflicts, .. } => conflicts
+                        .iter()
+                        .map(|conflict| MergeConflict {
+                            name: name.clone(),
+                            field: Some(conflict.field_name()),
+                            ours: conflict.ours.as_ref().map(Self::to_text),
+                            theirs: conflict.theirs.as_ref().map(Self::to_text),
+                        })
+                        .collect(),
+                    MergedFile::Whole { ours, theirs, .. } => vec![MergeConflict {
+                        name,
+                        field: None,
+                        ours: ours.as_ref().map(|_| "(changed)".to_string()),
+                        theirs: theirs.as_ref().map(|_| "(changed)".to_string()),
+                    }],
+                }
+            })
+            .collect()
+    }
+
+    fn to_text(value: &Value) -> String {
+        match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl CredentialsProvider {
+    /// Merges the local and the remote branch, decrypting the secrets git cannot merge and merging their fields.
+    pub(super) fn prepare_merge(&self, remote_name: &str, local_oid: Oid, remote_oid: Oid) -> Result<PendingMerge> {
+        let local_commit = self.repository.find_commit(local_oid)?;
+        let remote_commit = self.repository.find_commit(remote_oid)?;
+        let index = self.repository.merge_commits(&local_commit, &remote_commit, None)?;
+
+        let mut files = Vec::new();
+        for conflict in index.conflicts()? {
+            files.push(self.merge_conflicting_file(&conflict?)?);
+        }
+
+        Ok(PendingMerge {
+            local_oid,
+            remote_oid,
+            remote_name: remote_name.to_string(),
+            files,
+        })
+    }
+
+    fn merge_conflicting_file(&self, conflict: &IndexConflict) -> Result<MergedFile> {
+        let entry_path = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.path.clone())
+            .next()
+            .ok_or_else(|| anyhow!("Merge conflict without a path"))?;
+        let path = PathBuf::from(String::from_utf8(entry_path)?);
+
+        let read_blob = |entry: &Option<IndexEntry>| -> Result<Option<Vec<u8>>> {
+            entry.as_ref().map(|entry| Ok(self.repository.find_blob(entry.id)?.content().to_vec())).transpose()
+        };
+        let (base, ours, theirs) = (read_blob(&conflict.ancestor)?, read_blob(&conflict.our)?, read_blob(&conflict.their)?);
+
+        let is_attachment = path.parent().is_some_and(|folder| folder.to_string_lossy().ends_with(ATTACHMENTS_SUFFIX));
+        let format = SecretFileFormat::of(&path).ok().filter(|_| !is_attachment);
+
+        match (format, ours, theirs) {
+            (Some(format), Some(ours), Some(theirs)) => {
+                let parse = |ciphertext: &[u8]| -> Result<Secret> { Secret::parse(&String::from_utf8(self.decrypt(format, ciphertext)?)?) };
+                let base_secret = base.as_deref().map(parse).transpose()?.unwrap_or_default();
+                let our_secret = parse(&ours)?;
+                let their_secret = parse(&theirs)?;
+
+                let (fields, conflicts) = merge_fields(&base_secret.fields, &our_secret.fields, &their_secret.fields);
+                Ok(MergedFile::Secret {
+                    path,
+                    secret: our_secret.with_fields(fields),
+                    conflicts,
+                })
+            }
+            (_, ours, theirs) => Ok(MergedFile::Whole { path, ours, theirs }),
+        }
+    }
+
+    /// Applies one resolution per conflict, re-encrypts the merged secrets and commits the result with both
+    /// branches as parents.
+    pub(super) fn complete_merge(&self, pending_merge: &PendingMerge, resolutions: &[MergeSide]) -> Result<()> {
+        if self.get_parent_commit()?.map(|commit| commit.id()) != Some(pending_merge.local_oid) {
+            return Err(anyhow!("The secrets changed since the merge was prepared, sync again."));
+        }
+        if resolutions.len() != pending_merge.conflicts().len() {
+            return Err(anyhow!("Every conflict must be resolved before merging."));
+        }
+
+        let local_commit = self.repository.find_commit(pending_merge.local_oid)?;
+        let remote_commit = self.repository.find_commit(pending_merge.remote_oid)?;
+        let mut index = self.repository.merge_commits(&local_commit, &remote_commit, None)?;
+        let mut resolutions = resolutions.iter();
+
+        for file in &pending_merge.files {
+            let content = match file {
+                MergedFile::Secret { path, secret, conflicts } => {
+                    let mut secret = secret.clone();
+                    for (conflict, side) in conflicts.iter().zip(resolutions.by_ref()) {
+                        let value = match side {
+                            MergeSide::Ours => conflict.ours.as_ref(),
+                            MergeSide::Theirs => conflict.theirs.as_ref(),
+                        };
+                        resolve_conflict(&mut secret.fields, &conflict.field_path, value);
+                    }
+                    let (ciphertext, _) = self.encrypt_for(&self.path.join(path), secret.serialize()?.as_bytes())?;
+                    Some(ciphertext)
+                }
+                MergedFile::Whole { ours, theirs, .. } => match resolutions.next() {
+                    Some(MergeSide::Theirs) => theirs.clone(),
+                    _ => ours.clone(),
+                },
+            };
+
+            index.remove_path(file.path())?;
+            if let Some(content) = content {
+                let blob_id = self.repository.blob(&content)?;
+                index.add(&Self::index_entry(file.path(), blob_id, content.len()))?;
+            }
+        }
+        if index.has_conflicts() {
+            return Err(anyhow!("Some files could not be merged."));
+        }
+
+        let tree = self.repository.find_tree(index.write_tree_to(&self.repository)?)?;
+        self.repository
+            .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| anyhow!("Failed to write the merged secrets, are there uncommitted changes? {}", e))?;
+
+        let message = format!("Merge remote changes from {}", pending_merge.remote_name);
+        self.write_commit(&message, &tree, &[&local_commit, &remote_commit])?;
+        Ok(())
+    }
+
+    fn index_entry(path: &Path, blob_id: Oid, size: usize) -> IndexEntry {
+        IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: FILE_MODE,
+            uid: 0,
+            gid: 0,
+            file_size: size as u32,
+            id: blob_id,
+            flags: 0,
+            flags_extended: 0,
+            path: path.to_string_lossy().as_bytes().to_vec(),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Context, Result};
+use git2::build::CheckoutBuilder;
+use git2::{Cred, CredentialType, FetchOptions, Oid, PushOptions, Remote, RemoteCallbacks, Repository};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, TryRecvError};
+use std::thread;
+
+use super::CredentialsProvider;
+use crate::secret_store::SyncStatus;
+
+/// How many times libgit2 may ask for credentials before giving up, it keeps asking while they are rejected.
+const MAX_CREDENTIAL_ATTEMPTS: usize = 3;
+
+/// Remote the secrets repository is synchronized with.
+#[derive(Clone, Debug)]
+pub struct SyncSettings {
+    pub remote_name: String,
+    /// Registered as `remote_name` when set, otherwise the remote must already exist in the repository.
+    pub remote_url: Option<String>,
+    pub push_after_commit: bool,
+}
+
+impl CredentialsProvider {
+    /// Fetches the remote branch, then fast-forwards to it or pushes to it. Diverged histories are merged,
+    /// and the merge waits for the user when both sides changed the same fields.
+    pub(super) fn synchronize(&self) -> Result<SyncStatus> {
+        let Some(settings) = &self.sync_settings else {
+            return Ok(SyncStatus::Disabled);
+        };
+        self.pending_merge.replace(None);
+
+        let mut remote = self.find_remote(settings)?;
+        let branch_name = self.current_branch_name()?;
+        let fetch_refspec = format!("+refs/heads/{}:refs/remotes/{}/{}", branch_name, settings.remote_name, branch_name);
+        remote
+            .fetch(&[fetch_refspec.as_str()], Some(FetchOptions::new().remote_callbacks(Self::remote_callbacks())), None)
+            .context(format!("Failed to fetch from remote '{}'", settings.remote_name))?;
+
+        let remote_oid = self.repository.refname_to_id(&format!("refs/remotes/{}/{}", settings.remote_name, branch_name)).ok();
+        let local_oid = self.get_parent_commit()?.map(|commit| commit.id());
+
+        match (local_oid, remote_oid) {
+            (None, None) => Ok(SyncStatus::UpToDate),
+            (Some(_), None) => Self::push(settings, &mut remote, &branch_name),
+            (None, Some(remote_oid)) => self.fast_forward(&branch_name, remote_oid),
+            (Some(local_oid), Some(remote_oid)) => match self.repository.graph_ahead_behind(local_oid, remote_oid)? {
+                (0, 0) => Ok(SyncStatus::UpToDate),
+                (0, _) => self.fast_forward(&branch_name, remote_oid),
+                (_, 0) => Self::push(settings, &mut remote, &branch_name),
+                _ => self.merge(settings, &mut remote, &branch_name, local_oid, remote_oid),
+            },
+        }
+    }
+
+    fn merge(&self, settings: &SyncSettings, remote: &mut Remote, branch_name: &str, local_oid: Oid, remote_oid: Oid) -> Result<SyncStatus> {
+        let pending_merge = self
+            .prepare_merge(&settings.remote_name, local_oid, remote_oid)
+            .context("The local and remote histories have diverged and could not be merged")?;
+
+        let conflicts = pending_merge.conflicts().len();
+        if conflicts > 0 {
+            self.pending_merge.replace(Some(pending_merge));
+            return Ok(SyncStatus::Conflicted { conflicts });
+        }
+
+        self.complete_merge(&pending_merge, &[])?;
+        Self::push(settings, remote, branch_name)
+    }
+
+    /// Pushes a commit that was just made on another thread, with its own handle on the repository, so a slow
+    /// remote does not block the UI. A failed push only changes the sync status since the commit itself is kept.
+    pub(super) fn push_after_commit(&self) {
+        let Some(settings) = self.sync_settings.clone().filter(|settings| settings.push_after_commit) else {
+            return;
+        };
+        let branch_name = match self.find_remote(&settings).and_then(|_| self.current_branch_name()) {
+            Ok(branch_name) => branch_name,
+            Err(e) => {
+                log::error!("Failed to push to remote '{}': {:#?}", settings.remote_name, e);
+                self.sync_status.replace(SyncStatus::Failed(format!("{:#}", e)));
+                return;
+            }
+        };
+
+        let repository_path = self.repository.path().to_path_buf();
+        let (sender, push_result) = channel();
+        thread::spawn(move || {
+            let status = Repository::open(&repository_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|repository| {
+                    let mut remote = repository.find_remote(&settings.remote_name)?;
+                    Self::push(&settings, &mut remote, &branch_name)
+                })
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to push to remote '{}': {:#?}", settings.remote_name, e);
+                    SyncStatus::Failed(format!("{:#}", e))
+                });
+            let _ = sender.send(status);
+        });
+
+        self.pending_push.replace(Some(Rc::new(push_result)));
+        self.sync_status.replace(SyncStatus::Pushing);
+    }
+
+    /// Whether the background push is still running, a sync waits for it since both update the same refs.
+    pub(super) fn is_pushing(&self) -> bool {
+        self.take_push_result();
+        self.pending_push.borrow().is_some()
+    }
+
+    /// Takes over the outcome of the background push once it finished.
+    pub(super) fn take_push_result(&self) {
+        let status = match self.pending_push.borrow().as_ref().map(|push_result| push_result.try_recv()) {
+            Some(Ok(status)) => status,
+            Some(Err(TryRecvError::Disconnected)) => SyncStatus::Failed("The push to the remote stopped unexpectedly.".to_string()),
+            Some(Err(TryRecvError::Empty)) | None => return,
+        };
+        self.pending_push.replace(None);
+        self.sync_status.replace(status);
+    }
+
+    /// Registers the configured remote when it does not exist yet. An existing remote pointing elsewhere is never
+    /// changed, since pushing the secrets to a different URL than the user set up must not happen silently.
+    fn find_remote(&self, settings: &SyncSettings) -> Result<Remote<'_>> {
+        if let Some(remote_url) = &settings.remote_url {
+            match self.repository.find_remote(&settings.remote_name) {
+                Ok(remote) if remote.url() == Some(remote_url.as_str()) => {}
+                Ok(remote) => {
+                    return Err(anyhow!(
+                        "Remote '{}' of the secrets repository points to '{}' instead of the configured '{}'. Change the remote or the configured URL so that they match.",
+                        settings.remote_name,
+                        remote.url().unwrap_or_default(),
+                        remote_url
+                    ));
+                }
+                Err(_) => {
+                    self.repository.remote(&settings.remote_name, remote_url)?;
+                }
+            }
+        }
+        self.repository
+            .find_remote(&settings.remote_name)
+            .context(format!("Remote '{}' is not configured in the secrets repository", settings.remote_name))
+    }
+
+    /// Name of the branch HEAD points to, which also works before the first commit.
+    pub(super) fn current_branch_name(&self) -> Result<String> {
+        let head = self.repository.find_reference("HEAD")?;
+        let branch_reference = head.symbolic_target().ok_or_else(|| anyhow!("HEAD of the secrets repository is detached."))?;
+        Ok(branch_reference.trim_start_matches("refs/heads/").to_string())
+    }
+
+    fn fast_forward(&self, branch_name: &str, remote_oid: Oid) -> Result<SyncStatus> {
+        let branch_reference = format!("refs/heads/{}", branch_name);
+        let remote_commit = self.repository.find_commit(remote_oid)?;
+
+        self.repository
+            .checkout_tree(remote_commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .context("Failed to update the secrets with the remote changes, are there uncommitted changes?")?;
+        self.repository.reference(&branch_reference, remote_oid, true, "Fast-forward to remote")?;
+        self.repository.set_head(&branch_reference)?;
+        Ok(SyncStatus::UpToDate)
+    }
+
+    fn push(settings: &SyncSettings, remote: &mut Remote, branch_name: &str) -> Result<SyncStatus> {
+        let push_refspec = format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
+        let rejection: RefCell<Option<String>> = RefCell::new(None);
+
+        let mut callbacks = Self::remote_callbacks();
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(status) = status {
+                rejection.replace(Some(format!("{} was rejected: {}", reference, status)));
+            }
+            Ok(())
+        });
+        remote
+            .push(&[push_refspec.as_str()], Some(PushOptions::new().remote_callbacks(callbacks)))
+            .context(format!("Failed to push to remote '{}'", settings.remote_name))?;
+
+        match rejection.into_inner() {
+            Some(rejection) => Err(anyhow!("Remote '{}' refused the push, {}", settings.remote_name, rejection)),
+            None => Ok(SyncStatus::UpToDate),
+        }
+    }
+
+    /// Authenticates with the SSH agent for SSH remotes and with the git credential helpers for HTTPS remotes.
+    fn remote_callbacks<'a>() -> RemoteCallbacks<'a> {
+        let mut credential_attempts = 0;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username, allowed_types| {
+            credential_attempts += 1;
+            if credential_attempts > MAX_CREDENTIAL_ATTEMPTS {
+                return Err(git2::Error::from_str("Authentication with the remote failed"));
+            }
+
+            if allowed_types.contains(CredentialType::SSH_KEY) {
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                Cred::credential_helper(&git2::Config::open_default()?, url, username)
+            } else {
+                Cred::default()
+            }
+        });
+        callbacks
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{anyhow, Result};
+use git2::build::CheckoutBuilder;
+use git2::{Commit, Oid};
+
+use super::{CredentialsProvider, COMMITTER_EMAIL};
+
+const UNDO_PREFIX: &str = "Undo: ";
+/// Body line naming the commit an undo reverted, as `git revert` writes it.
+const REVERTS_COMMIT_PREFIX: &str = "This reverts commit ";
+
+impl CredentialsProvider {
+    /// Reverts the most recent commit that was not undone yet with a new commit, so undoing again
+    /// goes one step further back instead of undoing the undo.
+    pub(super) fn undo_last_commit(&self) -> Result<String> {
+        let (head_commit, commit) = self.find_undoable_commit()?;
+        let summary = commit.summary().unwrap_or_default().to_string();
+
+        let mut index = self.repository.revert_commit(&commit, &head_commit, 0, None)?;
+        if index.has_conflicts() {
+            return Err(anyhow!("'{}' cannot be undone, the files it changed were changed again since.", summary));
+        }
+
+        let tree = self.repository.find_tree(index.write_tree_to(&self.repository)?)?;
+        self.repository
+            .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| anyhow!("Failed to undo '{}', are there uncommitted changes? {}", summary, e))?;
+
+        let message = format!("{}{}\n\n{}{}.", UNDO_PREFIX, summary, REVERTS_COMMIT_PREFIX, commit.id());
+        self.write_commit(&message, &tree, &[&head_commit])?;
+        self.push_after_commit();
+        Ok(summary)
+    }
+
+    /// The current commit and the commit undo reverts next, which must have been made by Rustillium, here or on
+    /// another machine. Commits made with git directly are refused.
+    pub(super) fn find_undoable_commit(&self) -> Result<(Commit<'_>, Commit<'_>)> {
+        let head_commit = self.get_parent_commit()?.ok_or_else(|| anyhow!("There is nothing to undo."))?;
+        let commit = self.last_undoable_commit(&head_commit)?;
+        let summary = commit.summary().unwrap_or_default().to_string();
+
+        if commit.committer().email() != Some(COMMITTER_EMAIL) {
+            return Err(anyhow!("'{}' was not made by Rustillium and cannot be undone.", summary));
+        }
+        match commit.parent_count() {
+            0 => return Err(anyhow!("'{}' is the first change of the vault and cannot be undone.", summary)),
+            1 => {}
+            _ => return Err(anyhow!("'{}' merges two histories and cannot be undone.", summary)),
+        }
+        Ok((head_commit, commit))
+    }
+
+    /// Follows the first parents from `head_commit`, skipping undo commits and the commits they reverted.
+    fn last_undoable_commit<'a>(&self, head_commit: &Commit<'a>) -> Result<Commit<'a>> {
+        let mut undone_ids = Vec::new();
+        let mut commit = head_commit.clone();
+        loop {
+            match Self::undone_commit_id(&commit) {
+                Some(undone_id) => undone_ids.push(undone_id),
+                None if !undone_ids.contains(&commit.id()) => return Ok(commit),
+                None => {}
+            }
+            commit = commit.parent(0).map_err(|_| anyhow!("There is nothing left to undo."))?;
+        }
+    }
+
+    fn undone_commit_id(commit: &Commit) -> Option<Oid> {
+        let message = commit.message()?;
+        if !message.starts_with(UNDO_PREFIX) || commit.committer().email() != Some(COMMITTER_EMAIL) {
+            return None;
+        }
+        message
+            .lines()
+            .find_map(|line| line.strip_prefix(REVERTS_COMMIT_PREFIX))
+            .and_then(|commit_id| Oid::from_str(commit_id.trim_end_matches('.')).ok())
+    }
+}
@@ -0,0 +1,1261 @@
+mod fs_watch;
+mod git_commit;
+mod git_merge;
+mod git_sync;
+mod git_undo;
+
+use anyhow::{anyhow, Context, Result};
+use git2::Delta;
+use git2::Oid;
+use git2::Repository;
+use git2::Sort;
+use gpgme::{Context as GpgmeContext, Key, PassphraseRequest, PinentryMode, Protocol, Validity, VerificationResult};
+use std::cell::RefCell;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+
+use crate::age_keys::AgeKeys;
+use crate::age_passphrase;
+use crate::secret::{Secret, SignatureStatus};
+use crate::secret_store::{
+    renamed_from, undelete_message, validate_attachment_name, validate_secret_name, DeletedSecret, MergeConflict, MergeSide, ReencryptedSecret,
+    SecretRevision, SecretStore, SyncStatus, ATTACHMENTS_SUFFIX,
+};
+use fs_watch::DirectoryWatcher;
+use git_merge::PendingMerge;
+
+pub use git_commit::CommitAuthor;
+pub use git_sync::SyncSettings;
+
+/// Length of the abbreviated commit ids written into commit messages, as git shows them.
+const SHORT_COMMIT_ID_LENGTH: usize = 7;
+
+/// Committer of every commit Rustillium makes, which tells them apart from commits made with git directly.
+const COMMITTER_NAME: &str = "Rustillium";
+const COMMITTER_EMAIL: &str = "rustillium@app.local";
+
+/// Name of the pass-style file listing the recipients of every secret in its directory and below.
+const GPG_ID_FILE_NAME: &str = ".gpg-id";
+/// Hex digits of the shortest trusted signer key id, a long key id, as shorter ones match many keys.
+const MIN_TRUSTED_KEY_ID_LENGTH: usize = 16;
+
+/// Files besides secrets and attachments that belong in the secrets directory.
+const KNOWN_FILE_NAMES: [&str; 3] = [GPG_ID_FILE_NAME, ".gitignore", ".gitattributes"];
+
+/// How the secrets of a vault are encrypted, chosen per vault in the configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EncryptionMode {
+    /// Encrypted to the GPG public keys of the recipients.
+    #[default]
+    PublicKey,
+    /// Encrypted with a passphrase entered once per session, no GPG keypair needed.
+    Passphrase,
+}
+
+/// File format of an encrypted secret or attachment, told apart by the file extension.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecretFileFormat {
+    /// OpenPGP through gpgme, `.gpg` files.
+    #[default]
+    Gpg,
+    /// [age](https://age-encryption.org/) through the `age` crate, `.age` files.
+    Age,
+}
+
+impl SecretFileFormat {
+    pub const ALL: [SecretFileFormat; 2] = [SecretFileFormat::Gpg, SecretFileFormat::Age];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SecretFileFormat::Gpg => "gpg",
+            SecretFileFormat::Age => "age",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.extension() == extension)
+    }
+
+    fn of(path: &Path) -> Result<Self> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::from_extension)
+            .ok_or_else(|| anyhow!("Unknown encrypted file format of {:?}", path))
+    }
+
+    /// Appends the extension instead of replacing it, so names such as `example.com` stay intact.
+    fn file_path(self, stem: &Path) -> PathBuf {
+        let mut file_path = stem.as_os_str().to_owned();
+        file_path.push(format!(".{}", self.extension()));
+        PathBuf::from(file_path)
+    }
+}
+
+#[derive(Clone)]
+pub struct CredentialsProvider {
+    path: PathBuf,
+    encryption_mode: EncryptionMode,
+    passphrase: RefCell<Option<String>>,
+    recipients: Vec<String>,
+    signing_key: Option<String>,
+    trusted_signers: Vec<String>,
+    age: Option<AgeKeys>,
+    new_secret_format: SecretFileFormat,
+    commit_author: CommitAuthor,
+    sign_commits: bool,
+    sync_settings: Option<SyncSettings>,
+    sync_status: RefCell<SyncStatus>,
+    /// Result of the push running in the background after the last commit, if any.
+    pending_push: RefCell<Option<Rc<Receiver<SyncStatus>>>>,
+    pending_merge: RefCell<Option<PendingMerge>>,
+    watcher: RefCell<Option<Rc<DirectoryWatcher>>>,
+    repository: Rc<Repository>,
+}
+
+impl CredentialsProvider {
+    pub fn new(directory_name: &str, recipients: &[String]) -> Self {
+        let path = PathBuf::from(directory_name);
+        let repository = Self::initialize_repository(&path).expect("Fatal: Failed to open or initialize secrets repository.");
+        Self {
+            path,
+            encryption_mode: EncryptionMode::PublicKey,
+            passphrase: RefCell::new(None),
+            recipients: recipients.to_vec(),
+            signing_key: None,
+            trusted_signers: Vec::new(),
+            age: None,
+            new_secret_format: SecretFileFormat::Gpg,
+            commit_author: CommitAuthor::default(),
+            sign_commits: false,
+            sync_settings: None,
+            sync_status: RefCell::new(SyncStatus::Disabled),
+            pending_push: RefCell::new(None),
+            pending_merge: RefCell::new(None),
+            watcher: RefCell::new(None),
+            repository: Rc::new(repository)
+        }
+    }
+
+    pub fn with_encryption_mode(mut self, encryption_mode: EncryptionMode) -> Self {
+        self.encryption_mode = encryption_mode;
+        self
+    }
+
+    /// Key used to sign secrets on save. Without it, the first recipient with a secret key in the keyring signs.
+    pub fn with_signing_key(mut self, signing_key: Option<String>) -> Self {
+        self.signing_key = signing_key;
+        self
+    }
+
+    /// Long key ids, fingerprints or e-mails whose signatures are accepted. Without them, every signature is from an unknown signer.
+    pub fn with_trusted_signers(mut self, trusted_signers: &[String]) -> Self {
+        self.trusted_signers = trusted_signers.to_vec();
+        self
+    }
+
+    /// Enables `.age` secrets encrypted to recipients, which are otherwise listed but cannot be opened. Passphrase
+    /// vaults open and write passphrase-encrypted `.age` secrets without it.
+    pub fn with_age(mut self, age: Option<AgeKeys>) -> Self {
+        self.age = age;
+        self
+    }
+
+    /// Format new secrets are created in, existing secrets keep theirs until converted.
+    pub fn with_new_secret_format(mut self, new_secret_format: SecretFileFormat) -> Self {
+        self.new_secret_format = new_secret_format;
+        self
+    }
+
+    /// Author of the commits, taken from the git configuration of the secrets repository where not set.
+    pub fn with_commit_author(mut self, commit_author: CommitAuthor) -> Self {
+        self.commit_author = commit_author;
+        self
+    }
+
+    /// GPG-signs every commit with the signing key, see [`CredentialsProvider::with_signing_key`].
+    pub fn with_commit_signing(mut self, sign_commits: bool) -> Self {
+        self.sign_commits = sign_commits;
+        self
+    }
+
+    /// Synchronizes the secrets repository with a git remote, nothing leaves the machine without it.
+    pub fn with_sync(mut self, sync_settings: Option<SyncSettings>) -> Self {
+        self.sync_status = RefCell::new(if sync_settings.is_some() { SyncStatus::NotSynced } else { SyncStatus::Disabled });
+        self.sync_settings = sync_settings;
+        self
+    }
+
+    fn initialize_repository(path: &PathBuf) -> Result<Repository> {
+        Ok(Repository::open(path).or_else(|_| Repository::init(path))?)
+    }
+
+    /// Commits only `secret_paths`, staging the ones that exist and removing the others, so a stray
+    /// plaintext file in the secrets directory never ends up in the history.
+    fn commit(&self, secret_paths: &[PathBuf], message: &str) -> Result<()> {
+        let parent_commit = self.get_parent_commit()?;
+
+        let tree_id = {
+            let mut index = self.repository.index()?;
+            for secret_path in secret_paths {
+                let relative_path = secret_path.strip_prefix(&self.path)?;
+                if SecretFileFormat::of(relative_path).is_err() {
+                    return Err(anyhow!("Refusing to commit {:?}, only encrypted .gpg and .age files are committed.", relative_path));
+                }
+
+                if secret_path.is_file() {
+                    index.add_path(relative_path)?;
+                } else if index.get_path(relative_path, 0).is_some() {
+                    index.remove_path(relative_path)?;
+                }
+            }
+            index.write()?;
+            index.write_tree()?
+        };
+
+        let tree = self.repository.find_tree(tree_id)?;
+
+        let parents = parent_commit.map(|c| vec![c]).unwrap_or_default();
+        let parent_commits_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+        self.write_commit(message, &tree, parent_commits_refs.as_slice())?;
+        Ok(())
+    }
+
+    /// Applies `change` to the working tree and commits the touched `secret_paths`. If either step fails,
+    /// they are restored to the last committed state so the vault stays consistent.
+    /// A successful commit is pushed when sync is configured to do so.
+    fn transact(&self, secret_paths: &[PathBuf], message: &str, change: impl FnOnce() -> Result<()>) -> Result<()> {
+        let result = change().and_then(|_| self.commit(secret_paths, message));
+
+        if let Err(e) = result {
+            if let Err(rollback_error) = self.rollback(secret_paths) {
+                log::error!("Failed to roll back {:?} to the last commit: {:#?}", secret_paths, rollback_error);
+            }
+            return Err(e);
+        }
+        self.push_after_commit();
+        Ok(())
+    }
+
+    fn rollback(&self, secret_paths: &[PathBuf]) -> Result<()> {
+        let head_tree = self.get_parent_commit()?.map(|commit| commit.tree()).transpose()?;
+        let mut index = self.repository.index()?;
+
+        for secret_path in secret_paths {
+            let relative_path = secret_path.strip_prefix(&self.path)?;
+            match head_tree.as_ref().and_then(|tree| tree.get_path(relative_path).ok()) {
+                Some(entry) => {
+                    let blob = self.repository.find_blob(entry.id())?;
+                    Self::write_atomically(secret_path, blob.content())?;
+                    index.add_path(relative_path)?;
+                }
+                None => {
+                    if secret_path.exists() {
+                        fs::remove_file(secret_path)?;
+                        self.remove_empty_folders(secret_path);
+                    }
+                    if index.get_path(relative_path, 0).is_some() {
+                        index.remove_path(relative_path)?;
+                    }
+                }
+            }
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    /// Writes through a temporary file in the same folder, synced to disk and renamed over `path`,
+    /// so a crash leaves either the old or the new content but never a truncated file.
+    fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+        let folder = path.parent().ok_or_else(|| anyhow!("Invalid secret path {:?}", path))?;
+        let file_name = path.file_name().ok_or_else(|| anyhow!("Invalid secret path {:?}", path))?;
+        fs::create_dir_all(folder)?;
+
+        let temporary_path = folder.join(format!(".{}.tmp", file_name.to_string_lossy()));
+        let write_result = fs::File::create(&temporary_path)
+            .and_then(|mut temporary_file| {
+                temporary_file.write_all(content)?;
+                temporary_file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temporary_path, path));
+
+        if let Err(e) = write_result {
+            let _ = fs::remove_file(&temporary_path);
+            return Err(e).context(format!("Failed to write {:?}", path));
+        }
+
+        if let Ok(folder) = fs::File::open(folder) {
+            let _ = folder.sync_all();
+        }
+        Ok(())
+    }
+
+
+    fn get_parent_commit(&self) -> Result<Option<git2::Commit<'_>>> {
+        match self.repository.head() {
+            Ok(head) => {
+                if let Some(oid) = head.target() {
+                    Ok(Some(self.repository.find_commit(oid)?))
+                } else {
+                    Ok(None)
+                }
+            }
+            Err(e) => {
+                if e.code() == git2::ErrorCode::UnbornBranch || e.code() == git2::ErrorCode::NotFound {
+                    Ok(None)
+                } else {
+                    Err(e).map_err(|e| e.into())
+                }
+            }
+        }
+    }
+
+    /// Walks `directory` recursively, skipping hidden entries such as `.git` and attachment folders,
+    /// and collects every `.gpg` and `.age` file as a slash-separated secret name relative to the secrets directory.
+    fn collect_secret_names(&self, directory: &Path, secret_names: &mut Vec<String>) -> Result<()> {
+        for entry in fs::read_dir(directory)?.filter_map(|entry| entry.ok()) {
+            let entry_path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.starts_with('.') || file_name.ends_with(ATTACHMENTS_SUFFIX) {
+                continue;
+            }
+
+            if entry_path.is_dir() {
+                self.collect_secret_names(&entry_path, secret_names)?;
+            } else if entry_path.is_file()
+                && SecretFileFormat::of(&entry_path).is_ok()
+                && let Some(secret_name) = self.to_secret_name(&entry_path)
+            {
+                secret_names.push(secret_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks `directory` recursively, skipping `.git`, and collects every file that is neither encrypted nor one of
+    /// [`KNOWN_FILE_NAMES`], relative to the secrets directory.
+    fn collect_unexpected_files(&self, directory: &Path, unexpected_files: &mut Vec<String>) -> Result<()> {
+        for entry in fs::read_dir(directory)?.filter_map(|entry| entry.ok()) {
+            let entry_path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            if entry_path.is_dir() {
+                if file_name != ".git" {
+                    self.collect_unexpected_files(&entry_path, unexpected_files)?;
+                }
+            } else if SecretFileFormat::of(&entry_path).is_err()
+                && !KNOWN_FILE_NAMES.contains(&file_name.as_str())
+                && let Ok(relative_path) = entry_path.strip_prefix(&self.path)
+            {
+                unexpected_files.push(relative_path.to_string_lossy().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// `None` for paths outside of the secrets directory, including those leaving it through `..`.
+    fn to_secret_name(&self, secret_path: &Path) -> Option<String> {
+        let relative_path = secret_path.strip_prefix(&self.path).ok()?.with_extension("");
+        let segments: Option<Vec<&str>> = relative_path
+            .components()
+            .map(|component| match component {
+                Component::Normal(segment) => segment.to_str(),
+                _ => None,
+            })
+            .collect();
+        segments.map(|segments| segments.join("/"))
+    }
+
+    /// Removes the now empty folders between a deleted secret and the secrets directory.
+    fn remove_empty_folders(&self, secret_path: &Path) {
+        for directory in secret_path.ancestors().skip(1) {
+            if directory == self.path || !directory.starts_with(&self.path) || fs::remove_dir(directory).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Returns the existing encrypted file for `stem` in whichever format it is stored, or the path a new file in `new_format` gets.
+    fn encrypted_file_path(stem: &Path, new_format: SecretFileFormat) -> PathBuf {
+        SecretFileFormat::ALL
+            .into_iter()
+            .map(|format| format.file_path(stem))
+            .find(|file_path| file_path.exists())
+            .unwrap_or_else(|| new_format.file_path(stem))
+    }
+
+    fn secret_path(&self, secret_name: &str) -> PathBuf {
+        Self::encrypted_file_path(&self.path.join(secret_name), self.new_secret_format)
+    }
+
+    fn secret_format(&self, secret_name: &str) -> SecretFileFormat {
+        SecretFileFormat::of(&self.secret_path(secret_name)).unwrap_or(self.new_secret_format)
+    }
+
+    fn attachments_folder(&self, secret_name: &str) -> PathBuf {
+        self.path.join(format!("{}{}", secret_name, ATTACHMENTS_SUFFIX))
+    }
+
+    /// New attachments are stored in the format of their secret.
+    fn attachment_path(&self, secret_name: &str, attachment_name: &str) -> PathBuf {
+        Self::encrypted_file_path(&self.attachments_folder(secret_name).join(attachment_name), self.secret_format(secret_name))
+    }
+
+    fn age(&self) -> Result<&AgeKeys> {
+        self.age.as_ref().ok_or_else(|| anyhow!("age is not configured: set 'age_identity_file' in the configuration."))
+    }
+
+    fn attachment_paths(&self, secret_name: &str) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .list_attachments(secret_name)?
+            .iter()
+            .map(|attachment_name| self.attachment_path(secret_name, attachment_name))
+            .collect())
+    }
+
+    /// In passphrase mode the passphrase is handed to gpg directly instead of asking through pinentry,
+    /// and gpg-agent must not cache it, otherwise a wrong passphrase would still unlock the vault.
+    fn new_context(&self) -> Result<GpgmeContext> {
+        let mut context = GpgmeContext::from_protocol(Protocol::OpenPgp)?;
+        if self.encryption_mode == EncryptionMode::Passphrase {
+            context.set_pinentry_mode(PinentryMode::Loopback)?;
+            context.set_flag("no-symkey-cache", "1")?;
+        }
+        Ok(context)
+    }
+
+    /// The passphrase of an unlocked passphrase vault, which protects its `.gpg` and `.age` files alike.
+    fn session_passphrase(&self) -> Result<String> {
+        if self.encryption_mode == EncryptionMode::PublicKey {
+            return Err(anyhow!("Passphrase-encrypted files can only be opened in a vault with encryption_mode = \"passphrase\"."));
+        }
+        self.passphrase.borrow().clone().ok_or_else(|| anyhow!("The vault is locked, unlock it with its passphrase first."))
+    }
+
+    fn with_passphrase<R>(&self, context: &mut GpgmeContext, operation: impl FnOnce(&mut GpgmeContext) -> Result<R>) -> Result<R> {
+        if self.encryption_mode == EncryptionMode::PublicKey {
+            return operation(context);
+        }
+
+        let passphrase = self.session_passphrase()?;
+        let provider = move |_: PassphraseRequest, out: &mut dyn Write| -> gpgme::Result<()> {
+            out.write_all(passphrase.as_bytes())?;
+            Ok(())
+        };
+        context.with_passphrase_provider(provider, operation)
+    }
+
+    fn decrypt_file(&self, secret_path: &Path) -> Result<Vec<u8>> {
+        let ciphertext = fs::read(secret_path).context(format!("Failed to open secret file {:?}", secret_path))?;
+        self.decrypt(SecretFileFormat::of(secret_path)?, &ciphertext)
+    }
+
+    fn decrypt(&self, format: SecretFileFormat, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if format == SecretFileFormat::Age {
+            if age_passphrase::is_passphrase_encrypted(ciphertext) {
+                return age_passphrase::decrypt(&self.session_passphrase()?, ciphertext);
+            }
+            return self.age()?.decrypt(ciphertext);
+        }
+
+        let mut context = self.new_context()?;
+        let mut plaintext = Vec::new();
+        self.with_passphrase(&mut context, |context| Ok(context.decrypt(ciphertext, &mut plaintext)?))
+            .context("Failed to decrypt GPG content")?;
+        Ok(plaintext)
+    }
+
+    fn decrypt_and_verify_file(&self, secret_path: &Path) -> Result<(Vec<u8>, SignatureStatus)> {
+        let mut context = GpgmeContext::from_protocol(Protocol::OpenPgp)?;
+        let mut secrets_file = fs::File::open(secret_path).context(format!("Failed to open secret file {:?}", secret_path))?;
+        let mut plaintext = Vec::new();
+        let (_, verification) = context.decrypt_and_verify(&mut secrets_file, &mut plaintext).context("Failed to decrypt GPG content")?;
+
+        Ok((plaintext, Self::to_signature_status(&mut context, &verification, &self.trusted_signers)))
+    }
+
+    /// A bad signature wins over everything else, then a trusted one, so a tampered file is never reported as valid.
+    /// Only the locally configured trusted signers are trusted: the `.gpg-id` files are part of the repository, so
+    /// anyone who can push to it could add their own key there.
+    fn to_signature_status(context: &mut GpgmeContext, verification: &VerificationResult, trusted_signers: &[String]) -> SignatureStatus {
+        let mut status = SignatureStatus::Unsigned;
+
+        for signature in verification.signatures() {
+            let fingerprint = signature.fingerprint().unwrap_or_default().to_string();
+            let signature_status = match signature.status() {
+                Ok(_) => match Self::find_trusted_signer(context, &fingerprint, signature.validity(), trusted_signers) {
+                    Some(signer) => SignatureStatus::Valid(signer),
+                    None => SignatureStatus::UnknownSigner(fingerprint),
+                },
+                Err(e) if e.code() == gpgme::Error::NO_PUBKEY.code() => SignatureStatus::UnknownSigner(fingerprint),
+                Err(_) => SignatureStatus::Bad,
+            };
+
+            status = match (status, signature_status) {
+                (SignatureStatus::Bad, _) | (_, SignatureStatus::Bad) => SignatureStatus::Bad,
+                (SignatureStatus::Valid(signer), _) | (_, SignatureStatus::Valid(signer)) => SignatureStatus::Valid(signer),
+                (_, signature_status) => signature_status,
+            };
+        }
+        status
+    }
+
+    /// Returns the signer's e-mail (or fingerprint) if the signing key matches one of the trusted signers.
+    fn find_trusted_signer(context: &mut GpgmeContext, fingerprint: &str, validity: Validity, trusted_signers: &[String]) -> Option<String> {
+        if validity == Validity::Never {
+            return None;
+        }
+        let key = context.get_key(fingerprint).ok()?;
+        let emails: Vec<String> = key.user_ids().filter_map(|user_id| user_id.email().ok().map(|email| email.to_string())).collect();
+
+        let is_trusted = trusted_signers.iter().any(|trusted_signer| Self::is_trusted_signer(trusted_signer, fingerprint, &emails, validity));
+        is_trusted.then(|| emails.into_iter().find(|email| !email.is_empty()).unwrap_or_else(|| fingerprint.to_string()))
+    }
+
+    /// Key ids of at least 16 hex digits and fingerprints pin the key itself. An e-mail is only a claim of the key's
+    /// user id, so it is accepted only when the key is fully valid in the local keyring.
+    fn is_trusted_signer(trusted_signer: &str, fingerprint: &str, emails: &[String], validity: Validity) -> bool {
+        let trusted_signer = trusted_signer.trim();
+        if trusted_signer.contains('@') {
+            return matches!(validity, Validity::Full | Validity::Ultimate) && emails.iter().any(|email| email.eq_ignore_ascii_case(trusted_signer));
+        }
+
+        let key_id = trusted_signer.trim_start_matches("0x").replace(' ', "");
+        key_id.len() >= MIN_TRUSTED_KEY_ID_LENGTH
+            && key_id.chars().all(|character| character.is_ascii_hexdigit())
+            && fingerprint.to_uppercase().ends_with(&key_id.to_uppercase())
+    }
+
+    /// Encrypts `plaintext` to the recipients of `secret_path` and signs it, returning the ciphertext and the fingerprints of the keys used.
+    /// In passphrase mode the secret is encrypted with the session passphrase instead, without recipients or a signature.
+    /// `.age` files are encrypted to the age recipients, or with the session passphrase in passphrase mode.
+    fn encrypt_for(&self, secret_path: &Path, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<String>)> {
+        if SecretFileFormat::of(secret_path)? == SecretFileFormat::Age {
+            if self.encryption_mode == EncryptionMode::Passphrase {
+                return Ok((age_passphrase::encrypt(&self.session_passphrase()?, plaintext)?, Vec::new()));
+            }
+            let age = self.age()?;
+            return Ok((age.encrypt(plaintext)?, age.recipient_names()));
+        }
+
+        let mut context = self.new_context()?;
+        if self.encryption_mode == EncryptionMode::Passphrase {
+            let mut ciphertext = Vec::new();
+            self.with_passphrase(&mut context, |context| Ok(context.encrypt_symmetric(plaintext, &mut ciphertext)?))?;
+            return Ok((ciphertext, Vec::new()));
+        }
+
+        let recipients = self.resolve_recipients(secret_path)?;
+        let keys = Self::find_recipient_keys(&mut context, &recipients)?;
+
+        let mut ciphertext = Vec::new();
+        match self.find_signing_key(&mut context, &recipients)? {
+            Some(signing_key) => {
+                context.add_signer(&signing_key)?;
+                context.sign_and_encrypt(&keys, plaintext, &mut ciphertext)?;
+            }
+            None => {
+                log::warn!("No secret key found to sign {:?}, saving it unsigned", secret_path);
+                context.encrypt(&keys, plaintext, &mut ciphertext)?;
+            }
+        }
+
+        let fingerprints = keys.iter().filter_map(|key| key.fingerprint().ok()).map(|fingerprint| fingerprint.to_string()).collect();
+        Ok((ciphertext, fingerprints))
+    }
+
+    fn save_secret(&self, secret_path: &Path, secret: &Secret) -> Result<()> {
+        let plaintext = secret.serialize()?;
+        let (ciphertext, _) = self.encrypt_for(secret_path, plaintext.as_bytes())?;
+
+        Self::write_atomically(secret_path, &ciphertext)
+    }
+
+    /// Returns the recipients from the nearest `.gpg-id` file between the secret's directory
+    /// and the secrets directory, falling back to the configured recipients.
+    fn resolve_recipients(&self, secret_path: &Path) -> Result<Vec<String>> {
+        let gpg_id_path = secret_path
+            .ancestors()
+            .skip(1)
+            .take_while(|directory| directory.starts_with(&self.path))
+            .map(|directory| directory.join(GPG_ID_FILE_NAME))
+            .find(|gpg_id_path| gpg_id_path.is_file());
+
+        let recipients = match gpg_id_path {
+            Some(gpg_id_path) => {
+                let gpg_ids = fs::read_to_string(&gpg_id_path).context(format!("Failed to read recipients from {:?}", gpg_id_path))?;
+                Self::parse_gpg_ids(&gpg_ids)
+            }
+            None => self.recipients.clone(),
+        };
+
+        if recipients.is_empty() {
+            return Err(anyhow!("No recipients configured: set 'recipients' in the configuration or add a {} file to the secrets directory.", GPG_ID_FILE_NAME));
+        }
+        Ok(recipients)
+    }
+
+    fn parse_gpg_ids(gpg_ids: &str) -> Vec<String> {
+        gpg_ids
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    fn find_signing_key(&self, context: &mut GpgmeContext, recipients: &[String]) -> Result<Option<Key>> {
+        let is_usable = |key: &Key| key.can_sign() && !key.is_revoked() && !key.is_expired() && !key.is_disabled() && !key.is_invalid();
+
+        match &self.signing_key {
+            Some(signing_key) => context
+                .find_secret_keys([signing_key.as_str()])?
+                .filter_map(Result::ok)
+                .find(is_usable)
+                .map(Some)
+                .ok_or_else(|| anyhow!("No usable secret key found for signing key '{}'.", signing_key)),
+            None => Ok(context
+                .find_secret_keys(recipients.iter().map(String::as_str))?
+                .filter_map(Result::ok)
+                .find(is_usable)),
+        }
+    }
+
+    fn find_recipient_keys(context: &mut GpgmeContext, recipients: &[String]) -> Result<Vec<Key>> {
+        let mut keys = Vec::new();
+        let mut missing_recipients = Vec::new();
+
+        for recipient in recipients {
+            let usable_key = context
+                .find_keys([recipient.as_str()])?
+                .filter_map(Result::ok)
+                .find(|key| key.can_encrypt() && !key.is_revoked() && !key.is_expired() && !key.is_disabled() && !key.is_invalid());
+
+            match usable_key {
+                Some(key) => keys.push(key),
+                None => missing_recipients.push(recipient.as_str()),
+            }
+        }
+
+        if !missing_recipients.is_empty() {
+            return Err(anyhow!("No usable GPG key found for recipient(s): {}", missing_recipients.join(", ")));
+        }
+        Ok(keys)
+    }
+
+    fn update_message(original_name: Option<&str>, new_name: &str) -> String {
+        let is_renaming = original_name.is_some() && original_name.unwrap() != new_name;
+        let is_creating = original_name.is_none();
+
+        if is_creating {
+            format!("Create secret: {}", new_name)
+        } else if is_renaming {
+            format!("Rename secret from {} to {}", original_name.unwrap(), new_name)
+        } else {
+            format!("Update secret: {}", new_name)
+        }
+    }
+
+    fn find_blob_id(commit: &git2::Commit, secret_path: &Path) -> Option<Oid> {
+        commit.tree().ok()?.get_path(secret_path).ok().map(|entry| entry.id())
+    }
+
+    /// Path relative to the secrets directory and blob of a secret in a commit, in whichever format it was stored.
+    fn find_secret_blob(commit: &git2::Commit, secret_name: &str) -> Option<(PathBuf, Oid)> {
+        SecretFileFormat::ALL
+            .into_iter()
+            .map(|format| format.file_path(Path::new(secret_name)))
+            .find_map(|secret_path| Self::find_blob_id(commit, &secret_path).map(|blob_id| (secret_path, blob_id)))
+    }
+
+    fn to_revision(&self, commit: &git2::Commit, secret_name: &str) -> SecretRevision {
+        let author = commit.author();
+        SecretRevision {
+            id: commit.id().to_string(),
+            secret_name: secret_name.to_string(),
+            time: commit.time().seconds(),
+            message: commit.summary().unwrap_or_default().to_string(),
+            author: Some(format!("{} <{}>", author.name().unwrap_or_default(), author.email().unwrap_or_default())),
+            commit_signature: Some(self.commit_signature_status(commit)),
+        }
+    }
+
+    /// Secret names of the secret files `commit` removed compared to its parent. Merges are skipped, the
+    /// deletion is found in the commit of the branch that made it.
+    fn removed_secret_names(&self, commit: &git2::Commit) -> Result<Vec<String>> {
+        if commit.parent_count() != 1 {
+            return Ok(Vec::new());
+        }
+
+        let parent_tree = commit.parent(0)?.tree()?;
+        let diff = self.repository.diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+        Ok(diff
+            .deltas()
+            .filter(|delta| delta.status() == Delta::Deleted)
+            .filter_map(|delta| delta.old_file().path().map(Path::to_path_buf))
+            .filter(|path| {
+                SecretFileFormat::of(path).is_ok()
+                    && path.components().all(|component| {
+                        let segment = component.as_os_str().to_string_lossy();
+                        !segment.starts_with('.') && !segment.ends_with(ATTACHMENTS_SUFFIX)
+                    })
+            })
+            .filter_map(|path| self.to_secret_name(&self.path.join(path)))
+            .collect())
+    }
+
+    /// Decrypts the attachments a secret had in `commit` and encrypts them again for the attachments folder of `new_name`.
+    fn reencrypt_attachments_of_revision(&self, commit: &git2::Commit, secret_name: &str, new_name: &str) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+        let attachments_folder = PathBuf::from(format!("{}{}", secret_name, ATTACHMENTS_SUFFIX));
+        let Ok(folder_entry) = commit.tree()?.get_path(&attachments_folder) else {
+            return Ok(Vec::new());
+        };
+
+        let mut attachments = Vec::new();
+        for entry in self.repository.find_tree(folder_entry.id())?.iter() {
+            let file_name = entry.name().ok_or_else(|| anyhow!("Attachment name of secret '{}' is not valid UTF-8", secret_name))?;
+            let blob = self.repository.find_blob(entry.id())?;
+            let plaintext = self.decrypt(SecretFileFormat::of(Path::new(file_name))?, blob.content())?;
+
+            let attachment_path = self.attachments_folder(new_name).join(file_name);
+            let (ciphertext, _) = self.encrypt_for(&attachment_path, &plaintext)?;
+            attachments.push((attachment_path, ciphertext));
+        }
+        Ok(attachments)
+    }
+
+    fn decrypt_revision(&self, revision: &SecretRevision) -> Result<Vec<u8>> {
+        let commit = self.repository.find_commit(Oid::from_str(&revision.id)?)?;
+        let (secret_path, blob_id) = Self::find_secret_blob(&commit, &revision.secret_name)
+            .ok_or_else(|| anyhow!("Secret '{}' does not exist in this version, it was deleted.", revision.secret_name))?;
+
+        let blob = self.repository.find_blob(blob_id)?;
+        self.decrypt(SecretFileFormat::of(&secret_path)?, blob.content())
+    }
+}
+
+impl SecretStore for CredentialsProvider {
+    fn load_secret_names(&self) -> Result<Vec<String>> {
+        let mut secret_names = Vec::new();
+        self.collect_secret_names(&self.path, &mut secret_names)?;
+
+        secret_names.sort();
+        Ok(secret_names)
+    }
+
+    fn load_secrets(&self, secret_name: &str) -> Result<Secret> {
+        let secret_path = self.secret_path(secret_name);
+        let (secrets_bytes, signature) = match (self.encryption_mode, SecretFileFormat::of(&secret_path)?) {
+            (EncryptionMode::PublicKey, SecretFileFormat::Gpg) => {
+                self.decrypt_and_verify_file(&secret_path).map(|(bytes, signature)| (bytes, Some(signature)))?
+            }
+            _ => (self.decrypt_file(&secret_path)?, None),
+        };
+
+        let secrets_content = String::from_utf8(secrets_bytes).context("Decrypted content is not valid UTF-8")?;
+        let mut secret = Secret::parse(&secrets_content)?;
+        secret.signature = signature;
+        Ok(secret)
+    }
+
+    fn update_secret(&self, original_name: Option<&str>, new_name: &str, secret: &Secret) -> Result<()> {
+        let is_renaming = original_name.is_some() && original_name.unwrap() != new_name;
+        let is_creating = original_name.is_none();
+
+        validate_secret_name(new_name)?;
+        if (is_renaming || is_creating)
+            && self.secret_path(new_name).exists() {
+                return Err(anyhow!("A secret with the name '{}' already exists.", new_name));
+            }
+
+        let format = original_name.map(|original_name| self.secret_format(original_name)).unwrap_or(self.new_secret_format);
+        let new_path = format.file_path(&self.path.join(new_name));
+
+        let old_path = original_name.map(|original_name| self.secret_path(original_name));
+        let moved_attachments: Vec<(PathBuf, PathBuf)> = match original_name.filter(|_| is_renaming) {
+            Some(original_name) => self
+                .list_attachments(original_name)?
+                .iter()
+                .map(|attachment_name| self.attachment_path(original_name, attachment_name))
+                .filter_map(|from| from.file_name().map(|file_name| self.attachments_folder(new_name).join(file_name)).map(|to| (from, to)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut touched_paths: Vec<PathBuf> = [Some(new_path.clone()), old_path.clone()].into_iter().flatten().collect();
+        moved_attachments.iter().for_each(|(from, to)| touched_paths.extend([from.clone(), to.clone()]));
+
+        self.transact(&touched_paths, &Self::update_message(original_name, new_name), || {
+            self.save_secret(&new_path, secret)?;
+
+            if let Some(old_path) = old_path.filter(|_| is_renaming) {
+                fs::remove_file(&old_path)?;
+                self.remove_empty_folders(&old_path);
+            }
+            for (from, to) in &moved_attachments {
+                if let Some(folder) = to.parent() {
+                    fs::create_dir_all(folder)?;
+                }
+                fs::rename(from, to)?;
+                self.remove_empty_folders(from);
+            }
+            Ok(())
+        })
+    }
+
+    fn delete_secret(&self, secret_name: &str) -> Result<()> {
+        let path = self.secret_path(secret_name);
+        let attachment_paths = self.attachment_paths(secret_name)?;
+        let touched_paths: Vec<PathBuf> = [path.clone()].into_iter().chain(attachment_paths.iter().cloned()).collect();
+
+        self.transact(&touched_paths, &format!("Delete secret: {}", secret_name), || {
+            for attachment_path in &attachment_paths {
+                fs::remove_file(attachment_path)?;
+                self.remove_empty_folders(attachment_path);
+            }
+            fs::remove_file(&path)?;
+            self.remove_empty_folders(&path);
+            Ok(())
+        })
+    }
+
+    fn history(&self, secret_name: &str) -> Result<Vec<SecretRevision>> {
+        let mut revisions = Vec::new();
+        if self.get_parent_commit()?.is_none() {
+            return Ok(revisions);
+        }
+
+        let mut current_name = secret_name.to_string();
+        let mut secret_path = self.secret_path(secret_name).strip_prefix(&self.path)?.to_path_buf();
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(Sort::TIME)?;
+
+        for oid in revwalk {
+            let commit = self.repository.find_commit(oid?)?;
+            let blob_id = Self::find_blob_id(&commit, &secret_path);
+            let parent_blob_ids: Vec<Option<Oid>> = commit.parents().map(|parent| Self::find_blob_id(&parent, &secret_path)).collect();
+
+            let is_changed = if parent_blob_ids.is_empty() {
+                blob_id.is_some()
+            } else {
+                parent_blob_ids.iter().all(|parent_blob_id| *parent_blob_id != blob_id)
+            };
+            if !is_changed {
+                continue;
+            }
+
+            let revision = self.to_revision(&commit, &current_name);
+            let message = revision.message.clone();
+            revisions.push(revision);
+
+            // Re-encryption leaves nothing for git's rename detection to match, so renames and format
+            // conversions are followed through the path the secret had in the parent commit instead.
+            let is_created = blob_id.is_some() && parent_blob_ids.iter().all(Option::is_none);
+            if is_created && let Some(parent) = commit.parents().next() {
+                let previous_name = renamed_from(&message, &current_name).unwrap_or(&current_name).to_string();
+                if let Some((previous_path, _)) = Self::find_secret_blob(&parent, &previous_name) {
+                    current_name = previous_name;
+                    secret_path = previous_path;
+                }
+            }
+        }
+        Ok(revisions)
+    }
+
+    fn load_secret_revision(&self, revision: &SecretRevision) -> Result<Secret> {
+        let plaintext = self.decrypt_revision(revision)?;
+        Secret::parse(&String::from_utf8(plaintext).context("Decrypted content is not valid UTF-8")?)
+    }
+
+    /// Encrypts the old content to the current recipients, so restoring never brings back a revoked key.
+    fn restore_secret(&self, secret_name: &str, revision: &SecretRevision) -> Result<()> {
+        let plaintext = self.decrypt_revision(revision)?;
+        let secret_path = self.secret_path(secret_name);
+        let (ciphertext, _) = self.encrypt_for(&secret_path, &plaintext)?;
+
+        let short_id = &revision.id[..revision.id.len().min(SHORT_COMMIT_ID_LENGTH)];
+        self.transact(std::slice::from_ref(&secret_path), &format!("Restore secret: {} to {}", secret_name, short_id), || {
+            Self::write_atomically(&secret_path, &ciphertext)
+        })
+    }
+
+    fn deleted_secrets(&self) -> Result<Vec<DeletedSecret>> {
+        let mut deleted_secrets = Vec::new();
+        if self.get_parent_commit()?.is_none() {
+            return Ok(deleted_secrets);
+        }
+
+        let existing_names = self.load_secret_names()?;
+        let mut seen_names: Vec<String> = Vec::new();
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(Sort::TIME)?;
+
+        for oid in revwalk {
+            let commit = self.repository.find_commit(oid?)?;
+            for secret_name in self.removed_secret_names(&commit)? {
+                // A format conversion removes the old file but keeps the secret.
+                if seen_names.contains(&secret_name) || Self::find_secret_blob(&commit, &secret_name).is_some() {
+                    continue;
+                }
+                seen_names.push(secret_name.clone());
+
+                let deletion = self.to_revision(&commit, &secret_name);
+                let is_renamed = deletion.message.starts_with(&format!("Rename secret from {} to ", secret_name));
+                if is_renamed || existing_names.contains(&secret_name) {
+                    continue;
+                }
+
+                let parent = commit.parent(0)?;
+                deleted_secrets.push(DeletedSecret {
+                    last_revision: self.to_revision(&parent, &secret_name),
+                    secret_name,
+                    deletion,
+                });
+            }
+        }
+        Ok(deleted_secrets)
+    }
+
+    /// Encrypts the last content and the attachments to the current recipients, in the format the secret was stored in.
+    fn undelete_secret(&self, deleted_secret: &DeletedSecret, new_name: &str) -> Result<()> {
+        validate_secret_name(new_name)?;
+        if self.secret_path(new_name).exists() {
+            return Err(anyhow!("A secret with the name '{}' already exists.", new_name));
+        }
+
+        let commit = self.repository.find_commit(Oid::from_str(&deleted_secret.last_revision.id)?)?;
+        let (old_path, _) = Self::find_secret_blob(&commit, &deleted_secret.secret_name)
+            .ok_or_else(|| anyhow!("Secret '{}' does not exist in this version.", deleted_secret.secret_name))?;
+        let secret_path = SecretFileFormat::of(&old_path)?.file_path(&self.path.join(new_name));
+        let (ciphertext, _) = self.encrypt_for(&secret_path, &self.decrypt_revision(&deleted_secret.last_revision)?)?;
+        let attachments = self.reencrypt_attachments_of_revision(&commit, &deleted_secret.secret_name, new_name)?;
+
+        let touched_paths: Vec<PathBuf> = [secret_path.clone()].into_iter().chain(attachments.iter().map(|(path, _)| path.clone())).collect();
+        self.transact(&touched_paths, &undelete_message(&deleted_secret.secret_name, new_name), || {
+            Self::write_atomically(&secret_path, &ciphertext)?;
+            attachments.iter().try_for_each(|(attachment_path, content)| Self::write_atomically(attachment_path, content))
+        })
+    }
+
+    fn list_attachments(&self, secret_name: &str) -> Result<Vec<String>> {
+        let attachments_path = self.attachments_folder(secret_name);
+        if !attachments_path.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut attachment_names: Vec<String> = fs::read_dir(&attachments_path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry_path| entry_path.is_file() && SecretFileFormat::of(entry_path).is_ok())
+            .filter_map(|entry_path| entry_path.file_stem().and_then(|stem| stem.to_str()).map(|stem| stem.to_string()))
+            .filter(|attachment_name| !attachment_name.starts_with('.'))
+            .collect();
+
+        attachment_names.sort();
+        attachment_names.dedup();
+        Ok(attachment_names)
+    }
+
+    fn load_attachment(&self, secret_name: &str, attachment_name: &str) -> Result<Vec<u8>> {
+        self.decrypt_file(&self.attachment_path(secret_name, attachment_name))
+    }
+
+    fn add_attachment(&self, secret_name: &str, attachment_name: &str, content: &[u8]) -> Result<()> {
+        validate_attachment_name(attachment_name)?;
+        if !self.secret_path(secret_name).exists() {
+            return Err(anyhow!("Secret '{}' does not exist.", secret_name));
+        }
+
+        let attachment_path = self.attachment_path(secret_name, attachment_name);
+        if attachment_path.exists() {
+            return Err(anyhow!("An attachment named '{}' already exists on secret '{}'.", attachment_name, secret_name));
+        }
+
+        let (ciphertext, _) = self.encrypt_for(&attachment_path, content)?;
+        self.transact(std::slice::from_ref(&attachment_path), &format!("Attach {} to secret: {}", attachment_name, secret_name), || {
+            Self::write_atomically(&attachment_path, &ciphertext)
+        })
+    }
+
+    fn remove_attachment(&self, secret_name: &str, attachment_name: &str) -> Result<()> {
+        let attachment_path = self.attachment_path(secret_name, attachment_name);
+        self.transact(std::slice::from_ref(&attachment_path), &format!("Remove attachment {} from secret: {}", attachment_name, secret_name), || {
+            fs::remove_file(&attachment_path)?;
+            self.remove_empty_folders(&attachment_path);
+            Ok(())
+        })
+    }
+
+    /// Secrets of a passphrase vault are not signed, `.age` secrets of a public key vault are loaded as unsigned.
+    fn signs_secrets(&self) -> bool {
+        self.encryption_mode == EncryptionMode::PublicKey
+    }
+
+    fn encryption_formats(&self) -> Vec<String> {
+        SecretFileFormat::ALL
+            .into_iter()
+            .filter(|format| *format == SecretFileFormat::Gpg || self.age.is_some() || self.encryption_mode == EncryptionMode::Passphrase)
+            .map(|format| format.extension().to_string())
+            .collect()
+    }
+
+    fn encryption_format(&self, secret_name: &str) -> Result<String> {
+        Ok(SecretFileFormat::of(&self.secret_path(secret_name))?.extension().to_string())
+    }
+
+    /// Writes the secret and its attachments in the new format and removes the old files in the same commit.
+    fn convert_secret(&self, secret_name: &str, format: &str) -> Result<()> {
+        let target_format = SecretFileFormat::from_extension(format).ok_or_else(|| anyhow!("Unknown encryption format '{}'.", format))?;
+        let source_format = self.secret_format(secret_name);
+        if source_format == target_format {
+            return Err(anyhow!("Secret '{}' is already stored as {}.", secret_name, format));
+        }
+
+        let old_paths: Vec<PathBuf> = [self.secret_path(secret_name)].into_iter().chain(self.attachment_paths(secret_name)?).collect();
+        let mut converted_files = Vec::new();
+        for old_path in &old_paths {
+            let plaintext = self.decrypt_file(old_path).context(format!("Failed to decrypt {:?}", old_path))?;
+            let new_path = target_format.file_path(&old_path.with_extension(""));
+            let (ciphertext, _) = self.encrypt_for(&new_path, &plaintext)?;
+            converted_files.push((new_path, ciphertext));
+        }
+
+        let touched_paths: Vec<PathBuf> = old_paths.iter().cloned().chain(converted_files.iter().map(|(path, _)| path.clone())).collect();
+        let message = format!("Convert secret {} from {} to {}", secret_name, source_format.extension(), target_format.extension());
+        self.transact(&touched_paths, &message, || {
+            for (new_path, ciphertext) in &converted_files {
+                Self::write_atomically(new_path, ciphertext)?;
+            }
+            old_paths.iter().try_for_each(fs::remove_file)?;
+            Ok(())
+        })
+    }
+
+    fn sync(&self) -> Result<SyncStatus> {
+        if self.is_pushing() {
+            return Ok(SyncStatus::Pushing);
+        }
+        let result = self.synchronize();
+        self.sync_status.replace(match &result {
+            Ok(status) => status.clone(),
+            Err(e) => SyncStatus::Failed(format!("{:#}", e)),
+        });
+        result
+    }
+
+    fn sync_status(&self) -> SyncStatus {
+        self.take_push_result();
+        self.sync_status.borrow().clone()
+    }
+
+    fn undoable_change(&self) -> Result<String> {
+        let (_, commit) = self.find_undoable_commit()?;
+        Ok(commit.summary().unwrap_or_default().to_string())
+    }
+
+    fn undo(&self) -> Result<String> {
+        self.undo_last_commit()
+    }
+
+    fn watch_external_changes(&self, on_change: Box<dyn Fn() + Send>) -> Result<()> {
+        self.start_watching(on_change)
+    }
+
+    fn take_external_changes(&self) -> Option<Vec<String>> {
+        self.take_changed_secret_names()
+    }
+
+    fn unexpected_files(&self) -> Vec<String> {
+        let mut unexpected_files = Vec::new();
+        if let Err(e) = self.collect_unexpected_files(&self.path, &mut unexpected_files) {
+            log::error!("Failed to look for unexpected files in {:?}: {:#?}", self.path, e);
+        }
+        unexpected_files.sort();
+        unexpected_files
+    }
+
+    /// Passphrase-encrypted `.age` secrets of a passphrase vault need no identity file.
+    fn missing_keys(&self) -> Option<String> {
+        if self.age.is_some() || self.encryption_mode == EncryptionMode::Passphrase {
+            return None;
+        }
+        let has_age_secrets = self
+            .load_secret_names()
+            .map(|secret_names| secret_names.iter().any(|secret_name| self.secret_format(secret_name) == SecretFileFormat::Age))
+            .unwrap_or(false);
+        if has_age_secrets { self.age().err().map(|e| e.to_string()) } else { None }
+    }
+
+    fn merge_conflicts(&self) -> Vec<MergeConflict> {
+        self.pending_merge.borrow().as_ref().map(PendingMerge::conflicts).unwrap_or_default()
+    }
+
+    /// Commits the merge, then syncs again to push it.
+    fn resolve_merge(&self, resolutions: &[MergeSide]) -> Result<()> {
+        let pending_merge = self.pending_merge.borrow().clone().ok_or_else(|| anyhow!("There is no merge waiting to be resolved."))?;
+        self.complete_merge(&pending_merge, resolutions)?;
+        self.pending_merge.replace(None);
+
+        self.sync().map(|_| ())
+    }
+
+    fn is_locked(&self) -> bool {
+        self.encryption_mode == EncryptionMode::Passphrase && self.passphrase.borrow().is_none()
+    }
+
+    /// Accepts the passphrase if it decrypts a `.gpg` or passphrase-encrypted `.age` secret, or any passphrase for
+    /// an empty vault. `.age` secrets encrypted to recipients cannot confirm it, so a vault with only those stays locked.
+    fn unlock(&self, passphrase: &str) -> Result<()> {
+        if self.encryption_mode == EncryptionMode::PublicKey {
+            return Ok(());
+        }
+
+        let secret_names = self.load_secret_names()?;
+        let Some(secret_path) = secret_names.iter().map(|secret_name| self.secret_path(secret_name)).find(|secret_path| {
+            match SecretFileFormat::of(secret_path) {
+                Ok(SecretFileFormat::Gpg) => true,
+                Ok(SecretFileFormat::Age) => fs::read(secret_path).is_ok_and(|ciphertext| age_passphrase::is_passphrase_encrypted(&ciphertext)),
+                Err(_) => false,
+            }
+        }) else {
+            if !secret_names.is_empty() {
+                return Err(anyhow!("The vault has no passphrase-encrypted secret to check the passphrase against."));
+            }
+            self.passphrase.replace(Some(passphrase.to_string()));
+            return Ok(());
+        };
+
+        self.passphrase.replace(Some(passphrase.to_string()));
+        if let Err(e) = self.decrypt_file(&secret_path) {
+            self.passphrase.replace(None);
+            log::error!("Failed to unlock the vault with secret {:?}: {:#?}", secret_path, e);
+            return Err(anyhow!("Wrong passphrase."));
+        }
+        Ok(())
+    }
+
+    fn reencrypt_secret(&self, secret_name: &str) -> Result<ReencryptedSecret> {
+        let secret_path = self.secret_path(secret_name);
+        let plaintext = self.decrypt_file(&secret_path)?;
+        let (ciphertext, mut recipient_fingerprints) = self.encrypt_for(&secret_path, &plaintext)?;
+
+        let mut attachments = Vec::new();
+        for attachment_name in self.list_attachments(secret_name)? {
+            let attachment_path = self.attachment_path(secret_name, &attachment_name);
+            let content = self.decrypt_file(&attachment_path).context(format!("Failed to decrypt attachment '{}'", attachment_name))?;
+            let (attachment_ciphertext, attachment_fingerprints) = self.encrypt_for(&attachment_path, &content)?;
+            attachments.push((attachment_name, attachment_ciphertext));
+            recipient_fingerprints.extend(attachment_fingerprints);
+        }
+
+        Ok(ReencryptedSecret {
+            secret_name: secret_name.to_string(),
+            ciphertext,
+            attachments,
+            recipient_fingerprints,
+        })
+    }
+
+    fn apply_reencryption(&self, reencrypted_secrets: &[ReencryptedSecret]) -> Result<()> {
+        let reencrypted_files: Vec<(PathBuf, &Vec<u8>)> = reencrypted_secrets
+            .iter()
+            .flat_map(|reencrypted| {
+                let secret_file = (self.secret_path(&reencrypted.secret_name), &reencrypted.ciphertext);
+                let attachment_files = reencrypted
+                    .attachments
+                    .iter()
+                    .map(|(attachment_name, ciphertext)| (self.attachment_path(&reencrypted.secret_name, attachment_name), ciphertext));
+                std::iter::once(secret_file).chain(attachment_files)
+            })
+            .collect();
+        let secret_paths: Vec<PathBuf> = reencrypted_files.iter().map(|(path, _)| path.clone()).collect();
+
+        let mut fingerprints: Vec<&str> = reencrypted_secrets
+            .iter()
+            .flat_map(|reencrypted| reencrypted.recipient_fingerprints.iter().map(String::as_str))
+            .collect();
+        fingerprints.sort();
+        fingerprints.dedup();
+
+        self.transact(&secret_paths, &format!("Re-encrypt vault to {}", fingerprints.join(", ")), || {
+            reencrypted_files
+                .iter()
+                .try_for_each(|(path, ciphertext)| Self::write_atomically(path, ciphertext))
+        })
+        .context("Failed to re-encrypt the vault, the secrets were restored to the last commit")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const FINGERPRINT: &str = "0123456789ABCDEF0123456789ABCDEF01234567";
+
+    fn emails() -> Vec<String> {
+        vec!["me@email.com".to_string()]
+    }
+
+    #[test]
+    fn fingerprints_and_long_key_ids_are_trusted() {
+        assert!(CredentialsProvider::is_trusted_signer(FINGERPRINT, FINGERPRINT, &emails(), Validity::Unknown));
+        assert!(CredentialsProvider::is_trusted_signer("0x89abcdef01234567", FINGERPRINT, &emails(), Validity::Unknown));
+        assert!(CredentialsProvider::is_trusted_signer("0123 4567 89AB CDEF 0123  4567 89AB CDEF 0123 4567", FINGERPRINT, &emails(), Validity::Unknown));
+    }
+
+    #[test]
+    fn short_or_foreign_key_ids_are_not_trusted() {
+        assert!(!CredentialsProvider::is_trusted_signer("7", FINGERPRINT, &emails(), Validity::Full));
+        assert!(!CredentialsProvider::is_trusted_signer("01234567", FINGERPRINT, &emails(), Validity::Full));
+        assert!(!CredentialsProvider::is_trusted_signer("FFFFFFFFFFFFFFFF", FINGERPRINT, &emails(), Validity::Full));
+        assert!(!CredentialsProvider::is_trusted_signer("", FINGERPRINT, &emails(), Validity::Full));
+    }
+
+    #[test]
+    fn emails_are_trusted_only_for_valid_keys() {
+        assert!(CredentialsProvider::is_trusted_signer("Me@Email.com", FINGERPRINT, &emails(), Validity::Full));
+        assert!(CredentialsProvider::is_trusted_signer("me@email.com", FINGERPRINT, &emails(), Validity::Ultimate));
+        assert!(!CredentialsProvider::is_trusted_signer("me@email.com", FINGERPRINT, &emails(), Validity::Unknown));
+        assert!(!CredentialsProvider::is_trusted_signer("me@email.com", FINGERPRINT, &emails(), Validity::Marginal));
+        assert!(!CredentialsProvider::is_trusted_signer("other@email.com", FINGERPRINT, &emails(), Validity::Full));
+    }
+
+    #[test]
+    fn nested_secret_names_are_valid() {
+        assert!(validate_secret_name("bank").is_ok());
+        assert!(validate_secret_name("work/aws/prod").is_ok());
+        assert!(validate_secret_name("work/my account").is_ok());
+    }
+
+    #[test]
+    fn names_escaping_or_hiding_in_the_store_are_invalid() {
+        assert!(validate_secret_name("..").is_err());
+        assert!(validate_secret_name("../bank").is_err());
+        assert!(validate_secret_name("work/../bank").is_err());
+        assert!(validate_secret_name("/bank").is_err());
+        assert!(validate_secret_name("work/").is_err());
+        assert!(validate_secret_name("work//bank").is_err());
+        assert!(validate_secret_name("work\\bank").is_err());
+        assert!(validate_secret_name(".git").is_err());
+        assert!(validate_secret_name("").is_err());
+        assert!(validate_secret_name(&format!("bank{}", ATTACHMENTS_SUFFIX)).is_err());
+    }
+
+    #[test]
+    fn secret_names_are_the_paths_within_the_store_without_extension() {
+        let directory = TempDir::new().unwrap();
+        let credentials_provider = CredentialsProvider::new(&directory.path().display().to_string(), &[]);
+
+        assert_eq!(credentials_provider.to_secret_name(&directory.path().join("bank.gpg")), Some("bank".to_string()));
+        assert_eq!(credentials_provider.to_secret_name(&directory.path().join("work/aws/prod.age")), Some("work/aws/prod".to_string()));
+        assert_eq!(credentials_provider.to_secret_name(&directory.path().join("work").join("aws").join("dev.gpg")), Some("work/aws/dev".to_string()));
+        assert_eq!(credentials_provider.to_secret_name(Path::new("/elsewhere/bank.gpg")), None);
+        assert_eq!(credentials_provider.to_secret_name(&directory.path().join("../bank.gpg")), None);
+    }
+}
@@ -1,7 +1,7 @@
 use std::rc::Rc;
 
-use crate::cache::CachedSecretsResult;
-use crate::credentials_provider::CredentialsProvider;
+use crate::cache::forget_secret;
+use crate::secret_store::SecretStore;
 use eframe::egui::{Align, CentralPanel, Id, Layout, Panel, TextEdit, Ui, Vec2, ViewportBuilder, ViewportId};
 
 const DELETE_SECRET_TITLE: &str = "Delete Secret";
@@ -9,7 +9,7 @@ const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
 const DELETE_BUTTON_LABEL: &str = "\u{1f5d1} Delete";
 
 pub struct DeleteSecretUI {
-    credentials_provider: Rc<CredentialsProvider>,
+    secret_store: Rc<dyn SecretStore>,
     secret_name: String,
     confirmation_text: String,
     open_dialog: bool,
@@ -17,9 +17,9 @@ pub struct DeleteSecretUI {
 }
 
 impl DeleteSecretUI {
-    pub fn new(credentials_provider: &Rc<CredentialsProvider>) -> Self {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
         Self {
-            credentials_provider: Rc::clone(credentials_provider),
+            secret_store: Rc::clone(secret_store),
             secret_name: "".to_string(),
             confirmation_text: "".to_string(),
             open_dialog: false,
@@ -87,7 +87,7 @@ impl DeleteSecretUI {
             return;
         }
 
-        match self.credentials_provider.delete_secret(&self.secret_name) {
+        match self.secret_store.delete_secret(&self.secret_name) {
             Ok(_) => {
                 Self::clear_ui_cache(ui, &self.secret_name);
                 self.close();
@@ -102,7 +102,67 @@ impl DeleteSecretUI {
         let secret_names_cache_id = Id::new("secret_names").with("cache");
         ui.memory_mut(|m| m.data.remove::<Vec<String>>(secret_names_cache_id));
 
-        let secret_cache_id = Id::new(secret_name).with("cache");
-        ui.memory_mut(|m| m.data.remove::<CachedSecretsResult>(secret_cache_id));
+        ui.memory_mut(|m| forget_secret(&mut m.data, secret_name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::{Secret, SecretFormat};
+    use crate::secret_store::MemorySecretStore;
+    use eframe::egui::{Context, RawInput};
+
+    fn store_with_secret(secret_name: &str) -> Rc<dyn SecretStore> {
+        let secret_store: Rc<dyn SecretStore> = Rc::new(MemorySecretStore::new());
+        secret_store.update_secret(None, secret_name, &Secret::new(Default::default(), SecretFormat::Toml)).unwrap();
+        secret_store
+    }
+
+    fn delete(delete_secret_ui: &mut DeleteSecretUI, context: &Context) {
+        let _ = context.run_ui(RawInput::default(), |ui| delete_secret_ui.handle_delete(ui));
+    }
+
+    #[test]
+    fn delete_after_typing_the_secret_name() {
+        let secret_store = store_with_secret("bank");
+        let context = Context::default();
+        let mut delete_secret_ui = DeleteSecretUI::new(&secret_store);
+        delete_secret_ui.open("bank");
+        delete_secret_ui.confirmation_text = "bank".to_string();
+        context.memory_mut(|m| m.data.insert_temp(Id::new("secret_names").with("cache"), vec!["bank".to_string()]));
+
+        delete(&mut delete_secret_ui, &context);
+
+        assert!(secret_store.load_secret_names().unwrap().is_empty());
+        assert!(!delete_secret_ui.open_dialog);
+        assert!(context.memory_mut(|m| m.data.get_temp::<Vec<String>>(Id::new("secret_names").with("cache"))).is_none());
+    }
+
+    #[test]
+    fn keep_secret_when_the_name_does_not_match() {
+        let secret_store = store_with_secret("bank");
+        let mut delete_secret_ui = DeleteSecretUI::new(&secret_store);
+        delete_secret_ui.open("bank");
+        delete_secret_ui.confirmation_text = "ban".to_string();
+
+        delete(&mut delete_secret_ui, &Context::default());
+
+        assert_eq!(secret_store.load_secret_names().unwrap(), vec!["bank".to_string()]);
+        assert!(delete_secret_ui.open_dialog);
+        assert!(delete_secret_ui.error_message.is_some());
+    }
+
+    #[test]
+    fn show_error_when_the_store_fails() {
+        let secret_store = store_with_secret("bank");
+        let mut delete_secret_ui = DeleteSecretUI::new(&secret_store);
+        delete_secret_ui.open("missing");
+        delete_secret_ui.confirmation_text = "missing".to_string();
+
+        delete(&mut delete_secret_ui, &Context::default());
+
+        assert!(delete_secret_ui.open_dialog);
+        assert!(delete_secret_ui.error_message.as_ref().is_some_and(|error| error.starts_with("Unable to delete secret")));
     }
 }
@@ -0,0 +1,188 @@
+use std::rc::Rc;
+
+use crate::cache::forget_secret;
+use crate::secret_history::{format_time, show_field_rows, to_field_rows};
+use crate::secret_store::{DeletedSecret, SecretStore};
+use eframe::egui::{Align, Button, CentralPanel, Id, Layout, Panel, ScrollArea, TextEdit, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const DELETED_SECRETS_TITLE: &str = "Deleted Secrets";
+const CLOSE_BUTTON_LABEL: &str = "\u{274c} Close";
+const UNDELETE_BUTTON_LABEL: &str = "\u{21ba} Undelete";
+
+/// Lists secrets that only live on in the history, shows their last content and brings them back.
+pub struct DeletedSecretsUI {
+    secret_store: Rc<dyn SecretStore>,
+    deleted_secrets: Vec<DeletedSecret>,
+    selected_secret: Option<usize>,
+    /// Last fields of the selected secret, or why they cannot be shown.
+    selected_fields: Result<Vec<(String, String)>, String>,
+    /// Name the selected secret is undeleted as, its original name unless that is taken.
+    new_name: String,
+    open_dialog: bool,
+    result_message: Option<String>,
+    error_message: Option<String>,
+}
+
+impl DeletedSecretsUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            deleted_secrets: Vec::new(),
+            selected_secret: None,
+            selected_fields: Ok(Vec::new()),
+            new_name: String::new(),
+            open_dialog: false,
+            result_message: None,
+            error_message: None,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.result_message = None;
+        self.load_deleted_secrets();
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.deleted_secrets = Vec::new();
+        self.selected_secret = None;
+        self.selected_fields = Ok(Vec::new());
+        self.new_name = String::new();
+        self.result_message = None;
+        self.error_message = None;
+    }
+
+    fn load_deleted_secrets(&mut self) {
+        self.selected_secret = None;
+        self.selected_fields = Ok(Vec::new());
+        self.new_name = String::new();
+        match self.secret_store.deleted_secrets() {
+            Ok(deleted_secrets) => {
+                self.deleted_secrets = deleted_secrets;
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.deleted_secrets = Vec::new();
+                self.error_message = Some(format!("Unable to load the deleted secrets: {:#}", e));
+            }
+        }
+    }
+
+    fn select_secret(&mut self, index: usize) {
+        let deleted_secret = &self.deleted_secrets[index];
+        self.selected_secret = Some(index);
+        self.new_name = deleted_secret.secret_name.clone();
+        self.result_message = None;
+        self.error_message = None;
+        self.selected_fields = self
+            .secret_store
+            .load_secret_revision(&deleted_secret.last_revision)
+            .map(|secret| to_field_rows(&secret.fields))
+            .map_err(|e| format!("{:#}", e));
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let deleted_secrets_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(760.0, 420.0))
+                .with_title(DELETED_SECRETS_TITLE)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("deleted_secrets_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, deleted_secrets_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("deleted_secrets_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CLOSE_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            let can_undelete = self.selected_secret.is_some() && self.selected_fields.is_ok();
+                            if ui.add_enabled(can_undelete, Button::new(UNDELETE_BUTTON_LABEL)).clicked() {
+                                self.handle_undelete(ui);
+                            }
+                            if self.selected_secret.is_some() {
+                                ui.add(TextEdit::singleline(&mut self.new_name).desired_width(220.0));
+                                ui.label("as");
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                Panel::left(Id::new("deleted_secrets_list_panel")).resizable(true).default_size(300.0).show_inside(ui, |ui| {
+                    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                        if self.deleted_secrets.is_empty() {
+                            ui.label("No deleted secrets.");
+                        }
+                        let mut clicked_secret = None;
+                        for (index, deleted_secret) in self.deleted_secrets.iter().enumerate() {
+                            let label = format!("{}  {}", format_time(deleted_secret.deletion.time), deleted_secret.secret_name);
+                            if ui.selectable_label(self.selected_secret == Some(index), label).clicked() {
+                                clicked_secret = Some(index);
+                            }
+                        }
+                        if let Some(index) = clicked_secret {
+                            self.select_secret(index);
+                        }
+                    });
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    if let Some(result) = &self.result_message {
+                        ui.label(result);
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+
+                    match (&self.selected_fields, self.selected_secret) {
+                        (_, None) => {
+                            ui.label("Select a secret to view its last content.");
+                        }
+                        (Ok(fields), Some(index)) => {
+                            ui.label(format!("Deleted by: {}", self.deleted_secrets[index].deletion.message));
+                            ui.add_space(6.0);
+                            show_field_rows(fields, ui);
+                        }
+                        (Err(error), Some(_)) => {
+                            ui.colored_label(ui.style().visuals.error_fg_color, error);
+                        }
+                    }
+                });
+            });
+        }
+    }
+
+    fn handle_undelete(&mut self, ui: &mut Ui) {
+        let Some(deleted_secret) = self.selected_secret.map(|index| self.deleted_secrets[index].clone()) else {
+            return;
+        };
+        let new_name = self.new_name.trim().to_string();
+
+        match self.secret_store.undelete_secret(&deleted_secret, &new_name) {
+            Ok(_) => {
+                Self::clear_ui_cache(ui, &new_name);
+                self.load_deleted_secrets();
+                self.result_message = Some(format!("Undeleted '{}' as '{}'.", deleted_secret.secret_name, new_name));
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to undelete the secret: {:#}", e));
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui, secret_name: &str) {
+        let secret_names_cache_id = Id::new("secret_names").with("cache");
+        ui.memory_mut(|m| {
+            m.data.remove::<Vec<String>>(secret_names_cache_id);
+            forget_secret(&mut m.data, secret_name);
+        });
+    }
+}
@@ -1,39 +1,188 @@
 use config::Config;
-use std::{env::var as environment_variable, rc::Rc};
+use std::{env::var as environment_variable, path::Path, rc::Rc};
 
 
+pub mod age_keys;
+pub mod age_passphrase;
+pub mod attachment;
+pub mod breach_check;
+pub mod breached_passwords;
 pub mod cache;
+pub mod convert_secret;
 pub mod credentials_provider;
 pub mod delete_secret;
+pub mod deleted_secrets;
+pub mod merge_conflicts;
 pub mod modify_secret;
+pub mod passphrase_generator;
+pub mod password_generator;
+pub mod password_strength;
+pub mod rotate_keys;
+pub mod secret;
+pub mod secret_history;
+pub mod secret_merge;
+pub mod secret_store;
+pub mod security_report;
+pub mod strength_meter;
 pub mod view_secret;
 pub mod totp_provider;
+pub mod undo_change;
+pub mod unlock;
 
-use crate::{credentials_provider::CredentialsProvider, view_secret::ViewSecretUI};
+use crate::{
+    age_keys::AgeKeys,
+    breached_passwords::BreachedPasswords,
+    credentials_provider::{CommitAuthor, CredentialsProvider, EncryptionMode, SecretFileFormat, SyncSettings},
+    passphrase_generator::Wordlist,
+    password_generator::PasswordPolicy,
+    password_strength::PasswordFieldNames,
+    secret_store::{MemorySecretStore, SecretStore, SyncStatus},
+    security_report::DEFAULT_PASSWORD_MAX_AGE_DAYS,
+    view_secret::ViewSecretUI,
+};
 
 fn main() -> eframe::Result {
     env_logger::init();
-    let credentials_provider = configure_credential_provider();
+    let config = read_configuration();
+    let secret_store = configure_secret_store(&config);
     let version = env!("CARGO_PKG_VERSION");
 
     let version = version.to_string();
-    let view_secret_ui = ViewSecretUI::new(&Rc::new(credentials_provider), version.clone());
+    let view_secret_ui = ViewSecretUI::new(&secret_store, version.clone())
+        .with_password_generator(configure_password_policies(&config), configure_wordlists(&config))
+        .with_password_field_names(configure_password_field_names(&config))
+        .with_breached_passwords(configure_breached_passwords(&config))
+        .with_password_max_age_days(config.get_int("password_max_age_days").map_or(DEFAULT_PASSWORD_MAX_AGE_DAYS, |days| days.max(0) as u64));
 
     view_secret_ui.run(version)
 }
 
-fn configure_credential_provider() -> CredentialsProvider {
+fn read_configuration() -> Config {
     let mut config_path = environment_variable("HOME").unwrap_or(".".to_string());
     config_path.push_str("/.config/rustillium/config.toml");
 
-    let config = Config::builder()
+    Config::builder()
         .add_source(config::File::with_name(&config_path).required(false))
         .add_source(config::Environment::with_prefix("RUSTILLIUM"))
         .build()
-        .expect("Cannot read configuration");
+        .expect("Cannot read configuration")
+}
+
+/// Reads a list from an array in the configuration file or from a comma separated environment variable, which
+/// is left a string so other settings keep their text as is.
+fn get_list(config: &Config, key: &str) -> Option<Vec<String>> {
+    if let Ok(list) = config.get::<Vec<String>>(key) {
+        return Some(list);
+    }
+    let list = config.get_string(key).ok()?;
+    Some(list.split(',').map(str::trim).filter(|entry| !entry.is_empty()).map(str::to_string).collect())
+}
+
+fn configure_secret_store(config: &Config) -> Rc<dyn SecretStore> {
+    let storage_backend = config.get_string("storage_backend").unwrap_or("gpg".to_string());
+    match storage_backend.as_str() {
+        "gpg" => Rc::new(configure_credential_provider(config)),
+        "memory" => Rc::new(MemorySecretStore::with_demo_secrets()),
+        unknown => panic!("Unknown storage_backend '{}' in the configuration, expected 'gpg' or 'memory'", unknown),
+    }
+}
+
+/// Reads the `[password_policies.<name>]` tables, settings left out keep the default policy's value.
+fn configure_password_policies(config: &Config) -> Vec<PasswordPolicy> {
+    let default_policy = PasswordPolicy::default();
+    let mut password_policies: Vec<PasswordPolicy> = config
+        .get_table("password_policies")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, settings)| {
+            let settings = settings.into_table().unwrap_or_else(|_| panic!("Password policy '{}' in the configuration must be a table", name));
+            let get_bool = |key: &str, default: bool| settings.get(key).and_then(|value| value.clone().into_bool().ok()).unwrap_or(default);
+            PasswordPolicy {
+                length: settings.get("length").and_then(|value| value.clone().into_uint().ok()).map_or(default_policy.length, |length| length as usize),
+                lowercase: get_bool("lowercase", default_policy.lowercase),
+                uppercase: get_bool("uppercase", default_policy.uppercase),
+                digits: get_bool("digits", default_policy.digits),
+                symbols: get_bool("symbols", default_policy.symbols),
+                exclude_ambiguous: get_bool("exclude_ambiguous", default_policy.exclude_ambiguous),
+                require_each_class: get_bool("require_each_class", default_policy.require_each_class),
+                must_contain: settings.get("must_contain").and_then(|value| value.clone().into_string().ok()).unwrap_or_default(),
+                name,
+            }
+        })
+        .collect();
+
+    password_policies.sort_by_key(|policy| (policy.name != default_policy.name, policy.name.clone()));
+    if !password_policies.iter().any(|policy| policy.name == default_policy.name) {
+        password_policies.insert(0, default_policy);
+    }
+    password_policies
+}
 
+/// Reads the wordlist files listed in `passphrase_wordlists`, offered for passphrases next to the bundled one.
+fn configure_wordlists(config: &Config) -> Vec<Wordlist> {
+    get_list(config, "passphrase_wordlists")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| Wordlist::load(Path::new(&path)).unwrap_or_else(|e| panic!("Cannot read the passphrase wordlist: {:#}", e)))
+        .collect()
+}
+
+/// Reads `password_field_names`, the keys of the fields rated for strength, or keeps the default names.
+fn configure_password_field_names(config: &Config) -> PasswordFieldNames {
+    get_list(config, "password_field_names").map_or_else(PasswordFieldNames::default, PasswordFieldNames::new)
+}
+
+/// Opens the local copy of the Have I Been Pwned password hashes at `hibp_path`, if configured.
+fn configure_breached_passwords(config: &Config) -> Option<BreachedPasswords> {
+    let hibp_path = config.get_string("hibp_path").ok()?;
+    Some(BreachedPasswords::open(Path::new(&hibp_path)).unwrap_or_else(|e| panic!("Cannot use hibp_path from the configuration: {:#}", e)))
+}
+
+fn configure_credential_provider(config: &Config) -> CredentialsProvider {
     let secrets_directory = config.get_string("secrets_directory").unwrap_or("./enc".to_string());
-    let recipient_email = config.get_string("recipient_email").expect("recipient_email is not set in the configuration");
+    let encryption_mode = match config.get_string("encryption_mode").unwrap_or("public-key".to_string()).as_str() {
+        "public-key" => EncryptionMode::PublicKey,
+        "passphrase" => EncryptionMode::Passphrase,
+        unknown => panic!("Unknown encryption_mode '{}' in the configuration, expected 'public-key' or 'passphrase'", unknown),
+    };
+    let recipients: Vec<String> = get_list(config, "recipients")
+        .or_else(|| config.get_string("recipient_email").ok().map(|recipient_email| vec![recipient_email]))
+        .unwrap_or_default();
+    let signing_key = config.get_string("signing_key").ok();
+    let trusted_signers: Vec<String> = get_list(config, "trusted_signers").unwrap_or_default();
+    let age_recipients: Vec<String> = get_list(config, "age_recipients").unwrap_or_default();
+    let age = config.get_string("age_identity_file").ok().map(|identity_file| AgeKeys::new(&identity_file, &age_recipients));
+    let new_secrets_format = config.get_string("new_secrets_format").unwrap_or("gpg".to_string());
+    let new_secret_format = SecretFileFormat::from_extension(&new_secrets_format)
+        .unwrap_or_else(|| panic!("Unknown new_secrets_format '{}' in the configuration, expected 'gpg' or 'age'", new_secrets_format));
+    let commit_author = CommitAuthor {
+        name: config.get_string("git_author_name").ok(),
+        email: config.get_string("git_author_email").ok(),
+    };
+    let sign_commits = config.get_bool("sign_commits").unwrap_or(false);
+    let sync_remote_url = config.get_string("sync_remote_url").ok();
+    let sync_settings = (sync_remote_url.is_some() || config.get_string("sync_remote").is_ok()).then(|| SyncSettings {
+        remote_name: config.get_string("sync_remote").unwrap_or("origin".to_string()),
+        remote_url: sync_remote_url,
+        push_after_commit: config.get_bool("push_after_commit").unwrap_or(true),
+    });
+    let sync_on_startup = config.get_bool("sync_on_startup").unwrap_or(true);
+
+    let credentials_provider = CredentialsProvider::new(&secrets_directory, &recipients)
+        .with_encryption_mode(encryption_mode)
+        .with_signing_key(signing_key)
+        .with_trusted_signers(&trusted_signers)
+        .with_age(age)
+        .with_new_secret_format(new_secret_format)
+        .with_commit_author(commit_author)
+        .with_commit_signing(sign_commits)
+        .with_sync(sync_settings);
 
-    CredentialsProvider::new(&secrets_directory, &recipient_email)
+    if sync_on_startup
+        && credentials_provider.sync_status() != SyncStatus::Disabled
+        && let Err(e) = credentials_provider.sync()
+    {
+        log::error!("Failed to synchronize the secrets on startup: {:#?}", e);
+    }
+    credentials_provider
 }
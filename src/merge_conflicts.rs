@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use crate::cache::forget_secret;
+use crate::secret_store::{MergeConflict, MergeSide, SecretStore};
+use eframe::egui::{Align, CentralPanel, Grid, Id, Layout, Panel, RichText, ScrollArea, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const MERGE_CONFLICTS_TITLE: &str = "Resolve Sync Conflicts";
+const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
+const MERGE_BUTTON_LABEL: &str = "\u{2714} Merge";
+const DELETED_LABEL: &str = "(deleted)";
+
+pub struct MergeConflictsUI {
+    secret_store: Rc<dyn SecretStore>,
+    conflicts: Vec<MergeConflict>,
+    resolutions: Vec<MergeSide>,
+    open_dialog: bool,
+    error_message: Option<String>,
+}
+
+impl MergeConflictsUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            conflicts: Vec::new(),
+            resolutions: Vec::new(),
+            open_dialog: false,
+            error_message: None,
+        }
+    }
+
+    /// Opens the dialog if the last sync left conflicts to resolve, keeping our side by default.
+    pub fn open_if_conflicted(&mut self) {
+        self.conflicts = self.secret_store.merge_conflicts();
+        self.resolutions = vec![MergeSide::Ours; self.conflicts.len()];
+        self.error_message = None;
+        self.open_dialog = !self.conflicts.is_empty();
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.conflicts = Vec::new();
+        self.resolutions = Vec::new();
+        self.error_message = None;
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let merge_conflicts_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(760.0, 420.0))
+                .with_title(MERGE_CONFLICTS_TITLE)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("merge_conflicts_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, merge_conflicts_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("merge_conflicts_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CANCEL_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.button(MERGE_BUTTON_LABEL).clicked() {
+                                self.handle_merge(ui);
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    ui.label("These changes were made both on this machine and on the remote. Choose which value to keep:");
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+                    ui.add_space(6.0);
+
+                    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                        Grid::new("merge_conflicts_grid").num_columns(3).striped(true).spacing([16.0, 6.0]).show(ui, |ui| {
+                            ui.label(RichText::new("Secret").strong());
+                            ui.label(RichText::new("This machine").strong());
+                            ui.label(RichText::new("Remote").strong());
+                            ui.end_row();
+
+                            for (conflict, resolution) in self.conflicts.iter().zip(self.resolutions.iter_mut()) {
+                                match &conflict.field {
+                                    Some(field) => ui.label(format!("{} \u{2192} {}", conflict.name, field)),
+                                    None => ui.label(&conflict.name),
+                                };
+                                ui.radio_value(resolution, MergeSide::Ours, conflict.ours.as_deref().unwrap_or(DELETED_LABEL));
+                                ui.radio_value(resolution, MergeSide::Theirs, conflict.theirs.as_deref().unwrap_or(DELETED_LABEL));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+            });
+        }
+    }
+
+    fn handle_merge(&mut self, ui: &mut Ui) {
+        match self.secret_store.resolve_merge(&self.resolutions) {
+            Ok(_) => {
+                Self::clear_ui_cache(ui, &self.conflicts);
+                self.close();
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to merge: {:#}", e));
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui, conflicts: &[MergeConflict]) {
+        let secret_names_cache_id = Id::new("secret_names").with("cache");
+        ui.memory_mut(|m| {
+            m.data.remove::<Vec<String>>(secret_names_cache_id);
+            conflicts.iter().for_each(|conflict| forget_secret(&mut m.data, &conflict.name));
+        });
+    }
+}
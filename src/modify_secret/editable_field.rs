@@ -0,0 +1,162 @@
+use anyhow::{anyhow, Result};
+use toml::value::Datetime;
+use toml::{Table, Value};
+
+const CREDENTIAL_FIELDS: &[&str] = &["username", "password"];
+const NOTES_FIELD: &str = "notes";
+
+/// TOML value types a field can be edited as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+    Array,
+    Table,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 7] = [
+        ValueKind::String,
+        ValueKind::Integer,
+        ValueKind::Float,
+        ValueKind::Boolean,
+        ValueKind::Datetime,
+        ValueKind::Array,
+        ValueKind::Table,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ValueKind::String => "text",
+            ValueKind::Integer => "integer",
+            ValueKind::Float => "float",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Datetime => "date/time",
+            ValueKind::Array => "array",
+            ValueKind::Table => "table",
+        }
+    }
+}
+
+/// A secret field as edited in the dialog: scalars are kept as text until saved so that
+/// half-typed numbers do not get lost, while the original TOML type is remembered.
+pub struct EditableField {
+    pub key: String,
+    pub value: EditableValue,
+}
+
+pub enum EditableValue {
+    Text(ValueKind, String),
+    Boolean(bool),
+    Table(Vec<EditableField>),
+}
+
+impl EditableField {
+    pub fn new(key: &str, value: EditableValue) -> Self {
+        Self { key: key.to_string(), value }
+    }
+}
+
+impl EditableValue {
+    pub fn text(text: &str) -> Self {
+        EditableValue::Text(ValueKind::String, text.to_string())
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::String(text) => EditableValue::Text(ValueKind::String, text.clone()),
+            Value::Integer(number) => EditableValue::Text(ValueKind::Integer, number.to_string()),
+            Value::Float(number) => EditableValue::Text(ValueKind::Float, number.to_string()),
+            Value::Boolean(flag) => EditableValue::Boolean(*flag),
+            Value::Datetime(datetime) => EditableValue::Text(ValueKind::Datetime, datetime.to_string()),
+            Value::Array(_) => EditableValue::Text(ValueKind::Array, value.to_string()),
+            Value::Table(table) => EditableValue::Table(to_editable_fields(table)),
+        }
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            EditableValue::Text(kind, _) => *kind,
+            EditableValue::Boolean(_) => ValueKind::Boolean,
+            EditableValue::Table(_) => ValueKind::Table,
+        }
+    }
+
+    /// Switches the field to another type, carrying the current text over where it makes sense.
+    pub fn convert_to(&self, kind: ValueKind) -> Self {
+        let text = match self {
+            EditableValue::Text(_, text) => text.clone(),
+            EditableValue::Boolean(flag) => flag.to_string(),
+            EditableValue::Table(_) => String::new(),
+        };
+
+        match kind {
+            ValueKind::Boolean => EditableValue::Boolean(text.trim() == "true"),
+            ValueKind::Table => EditableValue::Table(Vec::new()),
+            ValueKind::Array if text.trim().is_empty() => EditableValue::Text(kind, "[]".to_string()),
+            _ => EditableValue::Text(kind, text),
+        }
+    }
+
+    fn to_value(&self, key: &str) -> Result<Value> {
+        match self {
+            EditableValue::Text(ValueKind::Integer, text) => {
+                text.trim().parse().map(Value::Integer).map_err(|_| anyhow!("Field '{}' must be an integer.", key))
+            }
+            EditableValue::Text(ValueKind::Float, text) => {
+                text.trim().parse().map(Value::Float).map_err(|_| anyhow!("Field '{}' must be a number.", key))
+            }
+            EditableValue::Text(ValueKind::Datetime, text) => text
+                .trim()
+                .parse::<Datetime>()
+                .map(Value::Datetime)
+                .map_err(|_| anyhow!("Field '{}' must be a TOML date or time, e.g. 2024-01-31.", key)),
+            EditableValue::Text(ValueKind::Array, text) => Self::parse_array(text).ok_or_else(|| anyhow!("Field '{}' must be a TOML array, e.g. [\"a\", 1].", key)),
+            EditableValue::Text(_, text) => Ok(Value::String(text.clone())),
+            EditableValue::Boolean(flag) => Ok(Value::Boolean(*flag)),
+            EditableValue::Table(fields) => to_table(fields).map(Value::Table),
+        }
+    }
+
+    fn parse_array(text: &str) -> Option<Value> {
+        let mut document = format!("value = {}", text).parse::<Table>().ok()?;
+        document.remove("value").filter(Value::is_array)
+    }
+}
+
+/// Converts a TOML table into editable fields, credentials first, notes last and the rest sorted by name.
+pub fn to_editable_fields(table: &Table) -> Vec<EditableField> {
+    let mut fields: Vec<EditableField> = table.iter().map(|(key, value)| EditableField::new(key, EditableValue::from_value(value))).collect();
+
+    fields.sort_by_key(|field| (field_rank(&field.key), field.key.clone()));
+    fields
+}
+
+fn field_rank(key: &str) -> usize {
+    if key == NOTES_FIELD {
+        CREDENTIAL_FIELDS.len() + 1
+    } else {
+        CREDENTIAL_FIELDS.iter().position(|&k| k == key).unwrap_or(CREDENTIAL_FIELDS.len())
+    }
+}
+
+pub fn to_table(fields: &[EditableField]) -> Result<Table> {
+    let mut table = Table::new();
+    for field in fields {
+        if table.insert(field.key.clone(), field.value.to_value(&field.key)?).is_some() {
+            return Err(anyhow!("Field '{}' is defined more than once.", field.key));
+        }
+    }
+    Ok(table)
+}
+
+pub fn empty_credential_fields() -> Vec<EditableField> {
+    CREDENTIAL_FIELDS
+        .iter()
+        .chain([NOTES_FIELD].iter())
+        .map(|&key| EditableField::new(key, EditableValue::text("")))
+        .collect()
+}
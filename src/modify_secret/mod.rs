@@ -0,0 +1,391 @@
+mod editable_field;
+mod password_generator_ui;
+
+use std::rc::Rc;
+
+use crate::cache::forget_secret;
+use crate::modify_secret::editable_field::{empty_credential_fields, to_editable_fields, to_table, EditableField, EditableValue, ValueKind};
+use crate::modify_secret::password_generator_ui::PasswordGeneratorUI;
+use crate::passphrase_generator::Wordlist;
+use crate::password_generator::PasswordPolicy;
+use crate::password_strength::PasswordFieldNames;
+use crate::strength_meter::{cached_strength_estimate, show_strength_meter};
+use crate::secret::{Secret, SecretFormat};
+use crate::secret_store::SecretStore;
+use anyhow::Result;
+use eframe::egui::{Align, Button, CentralPanel, ComboBox, Id, Layout, Panel, ScrollArea, TextEdit, Ui, ViewportBuilder, ViewportId};
+
+const ADD_SECRET_TITLE: &str = "Add New Secret";
+const MODIFY_SECRET_TITLE: &str = "Modify Secret";
+const DELETE_BUTTON_LABEL: &str = " \u{1F5D1}";
+const ADD_BUTTON_LABEL: &str = "\u{2795} Add";
+const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
+const SAVE_BUTTON_LABEL: &str = "\u{1f4be} Save";
+const MAX_VALUE_ROWS: usize = 8;
+
+pub struct ModifySecretUI {
+    secret_store: Rc<dyn SecretStore>,
+    updated_secret_name: String,
+    original_secret_name: String,
+    dialog_secrets: Vec<EditableField>,
+    /// The secret as loaded, whose format and `pass` line order are kept on save.
+    original_secret: Secret,
+    convert_to_toml: bool,
+    open_dialog: bool,
+    title: String,
+    password_generator_ui: PasswordGeneratorUI,
+    password_field_names: PasswordFieldNames,
+    error_message: Option<String>,
+    /// Set when the secret to modify could not be loaded, saving would then replace it with empty fields.
+    load_failed: bool,
+}
+
+impl ModifySecretUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            updated_secret_name: "".to_string(),
+            original_secret_name: "".to_string(),
+            dialog_secrets: Vec::new(),
+            original_secret: Secret::default(),
+            convert_to_toml: false,
+            open_dialog: false,
+            title: ADD_SECRET_TITLE.to_string(),
+            password_generator_ui: PasswordGeneratorUI::new(Vec::new(), Vec::new()),
+            password_field_names: PasswordFieldNames::default(),
+            error_message: None,
+            load_failed: false,
+        }
+    }
+
+    /// Named policies offered by the password generator, the first one is preselected, and wordlists offered
+    /// for passphrases next to the bundled one.
+    pub fn with_password_generator(mut self, password_policies: Vec<PasswordPolicy>, wordlists: Vec<Wordlist>) -> Self {
+        self.password_generator_ui = PasswordGeneratorUI::new(password_policies, wordlists);
+        self
+    }
+
+    /// Keys of the fields that get a strength meter while typing.
+    pub fn with_password_field_names(mut self, password_field_names: PasswordFieldNames) -> Self {
+        self.password_field_names = password_field_names;
+        self
+    }
+
+    pub fn open(&mut self, secret_name: &str) {
+        self.original_secret_name = secret_name.to_string();
+        self.updated_secret_name = secret_name.to_string();
+        self.original_secret = Secret::default();
+        self.convert_to_toml = false;
+        self.error_message = None;
+        self.load_failed = false;
+        if secret_name.is_empty() {
+            self.title = ADD_SECRET_TITLE.to_string();
+            self.dialog_secrets = empty_credential_fields();
+        } else {
+            self.title = format!("{}: {}", MODIFY_SECRET_TITLE, secret_name);
+            self.dialog_secrets = self.load_secrets(secret_name).unwrap_or_else(|e| {
+                log::error!("Failed to load secret '{}': {:#?}", secret_name, e);
+                self.error_message = Some(format!("Unable to load secret: {:#}", e));
+                self.load_failed = true;
+                Vec::new()
+            });
+        }
+
+        self.open_dialog = true;
+    }
+
+    fn load_secrets(&mut self, secret_name: &str) -> Result<Vec<EditableField>> {
+        let secret = self.secret_store.load_secrets(secret_name)?;
+        let editable_fields = to_editable_fields(&secret.fields);
+        self.original_secret = secret;
+        Ok(editable_fields)
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.password_generator_ui.close();
+        self.original_secret_name = "".to_string();
+        self.error_message = None;
+    }
+
+    fn show_editable_section(&mut self, ui: &mut Ui) {
+        ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+            Self::show_fields(ui, &mut self.dialog_secrets, "", &mut self.password_generator_ui, &self.password_field_names);
+        });
+    }
+
+    /// Shows one level of fields; nested tables are indented below their key with their own add button.
+    fn show_fields(ui: &mut Ui, fields: &mut Vec<EditableField>, id_path: &str, password_generator_ui: &mut PasswordGeneratorUI, password_field_names: &PasswordFieldNames) {
+        let mut potential_index: Option<usize> = Option::None;
+
+        fields.iter_mut().enumerate().for_each(|(index, field)| {
+            let field_id = format!("{}{}", id_path, index);
+            if let (Some(password), EditableValue::Text(ValueKind::String, text)) = (password_generator_ui.take_inserted_password(&field_id), &mut field.value) {
+                *text = password;
+            }
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut field.key);
+                match &mut field.value {
+                    EditableValue::Text(ValueKind::String, text) => {
+                        let rows = text.lines().count().clamp(1, MAX_VALUE_ROWS);
+                        ui.add(TextEdit::multiline(text).desired_rows(rows));
+                        password_generator_ui.show_toggle_button(ui, &field_id);
+                    }
+                    EditableValue::Text(_, text) => {
+                        ui.text_edit_singleline(text);
+                    }
+                    EditableValue::Boolean(flag) => {
+                        ui.checkbox(flag, "");
+                    }
+                    EditableValue::Table(_) => {}
+                }
+                Self::show_kind_selector(ui, field, &field_id);
+                if ui.button(DELETE_BUTTON_LABEL).clicked() {
+                    potential_index = Some(index);
+                }
+            });
+
+            if let EditableValue::Text(ValueKind::String, text) = &field.value
+                && !text.is_empty()
+                && password_field_names.matches(&field.key)
+            {
+                let estimate = cached_strength_estimate(ui, Id::new("modify_secret_strength").with(&field_id), text);
+                show_strength_meter(ui, &estimate);
+            }
+            if password_generator_ui.is_open_for(&field_id) {
+                password_generator_ui.show(ui);
+            }
+            if let EditableValue::Table(nested_fields) = &mut field.value {
+                ui.indent(&field_id, |ui| {
+                    Self::show_fields(ui, nested_fields, &format!("{}.", field_id), password_generator_ui, password_field_names);
+                });
+            }
+        });
+
+        if let Some(index_to_remove) = potential_index {
+            fields.remove(index_to_remove);
+            password_generator_ui.close();
+        }
+
+        ui.add_space(6.0);
+        if ui.button(ADD_BUTTON_LABEL).clicked() {
+            let len = fields.len() + 1;
+            fields.push(EditableField::new(&format!("name{len}"), EditableValue::text(&format!("value{len}"))));
+        }
+    }
+
+    fn show_kind_selector(ui: &mut Ui, field: &mut EditableField, field_id: &str) {
+        let current_kind = field.value.kind();
+        let mut selected_kind = current_kind;
+
+        ComboBox::from_id_salt(("field_kind", field_id)).selected_text(current_kind.label()).show_ui(ui, |ui| {
+            ValueKind::ALL.iter().for_each(|&kind| {
+                ui.selectable_value(&mut selected_kind, kind, kind.label());
+            });
+        });
+
+        if selected_kind != current_kind {
+            field.value = field.value.convert_to(selected_kind);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let modify_secret_dialog = ViewportBuilder::default().with_title(self.title.as_str()).with_close_button(true).with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("modify_secret_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, modify_secret_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+                Panel::bottom(Id::new("modify_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Secret name: ");
+                        ui.text_edit_singleline(&mut self.updated_secret_name);
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CANCEL_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.add_enabled(!self.load_failed, Button::new(SAVE_BUTTON_LABEL)).clicked() {
+                                self.handle_save(ui);
+                            }
+                        })
+                    });
+                    ui.add_space(2.0);
+                });
+                CentralPanel::default().show_inside(ui, |ui| {
+                    if self.original_secret.format == SecretFormat::Pass {
+                        ui.checkbox(&mut self.convert_to_toml, "Convert from pass format to TOML on save");
+                        ui.add_space(6.0);
+                    }
+                    self.show_editable_section(ui);
+
+                    ui.with_layout(Layout::bottom_up(Align::LEFT), |ui| {
+                        if let Some(error) = &self.error_message {
+                            ui.colored_label(ui.style().visuals.error_fg_color, error);
+                        }
+                    });
+                });
+            });
+        }
+    }
+
+    fn handle_save(&mut self, ui: &mut Ui) {
+        if self.load_failed {
+            return;
+        }
+        if self.updated_secret_name.is_empty() {
+            self.error_message = Some("Secret name cannot be empty.".to_string());
+        } else {
+            let original_secret_name = if self.original_secret_name.is_empty() { None } else { Some(self.original_secret_name.as_str()) };
+
+            let save_result = to_table(&self.dialog_secrets).and_then(|fields| {
+                let mut secrets_to_save = self.original_secret.with_fields(fields);
+                if self.convert_to_toml {
+                    secrets_to_save.format = SecretFormat::Toml;
+                }
+                self.secret_store.update_secret(original_secret_name, &self.updated_secret_name, &secrets_to_save)
+            });
+            match save_result {
+                Ok(_) => {
+                    Self::clear_ui_cache(ui, original_secret_name);
+                    self.close();
+                }
+                Err(e) => {
+                    self.error_message = Some(format!("Unable to save secret: {}", e));
+                }
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui, original_secret_name: Option<&str>) {
+        let secret_names_cache_id = Id::new("secret_names").with("cache");
+        ui.memory_mut(|m| m.data.remove::<Vec<String>>(secret_names_cache_id));
+
+        if let Some(name) = original_secret_name {
+            ui.memory_mut(|m| forget_secret(&mut m.data, name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_store::MemorySecretStore;
+    use eframe::egui::{Context, RawInput};
+    use toml::Table;
+
+    fn store_with_secret(secret_name: &str, content: &str) -> Rc<dyn SecretStore> {
+        let secret_store: Rc<dyn SecretStore> = Rc::new(MemorySecretStore::new());
+        secret_store.update_secret(None, secret_name, &Secret::parse(content).unwrap()).unwrap();
+        secret_store
+    }
+
+    fn save(modify_secret_ui: &mut ModifySecretUI) {
+        let _ = Context::default().run_ui(RawInput::default(), |ui| modify_secret_ui.handle_save(ui));
+    }
+
+    fn set_field(modify_secret_ui: &mut ModifySecretUI, key: &str, text: &str) {
+        match modify_secret_ui.dialog_secrets.iter_mut().find(|field| field.key == key) {
+            Some(field) => field.value = EditableValue::text(text),
+            None => modify_secret_ui.dialog_secrets.push(EditableField::new(key, EditableValue::text(text))),
+        }
+    }
+
+    #[test]
+    fn save_new_secret() {
+        let secret_store: Rc<dyn SecretStore> = Rc::new(MemorySecretStore::new());
+        let mut modify_secret_ui = ModifySecretUI::new(&secret_store);
+        modify_secret_ui.open("");
+        modify_secret_ui.updated_secret_name = "bank".to_string();
+        set_field(&mut modify_secret_ui, "password", "s3cret");
+
+        save(&mut modify_secret_ui);
+
+        assert!(modify_secret_ui.error_message.is_none());
+        assert!(!modify_secret_ui.open_dialog);
+        let secret = secret_store.load_secrets("bank").unwrap();
+        assert_eq!(secret.format, SecretFormat::Toml);
+        assert_eq!(secret.fields["password"].as_str(), Some("s3cret"));
+    }
+
+    #[test]
+    fn save_renamed_secret() {
+        let secret_store = store_with_secret("bank", "password = \"s3cret\"\nport = 8443\n");
+        let mut modify_secret_ui = ModifySecretUI::new(&secret_store);
+        modify_secret_ui.open("bank");
+        modify_secret_ui.updated_secret_name = "finance/bank".to_string();
+        set_field(&mut modify_secret_ui, "password", "changed");
+
+        save(&mut modify_secret_ui);
+
+        assert!(!modify_secret_ui.open_dialog);
+        assert_eq!(secret_store.load_secret_names().unwrap(), vec!["finance/bank".to_string()]);
+        let secret = secret_store.load_secrets("finance/bank").unwrap();
+        assert_eq!(secret.fields["password"].as_str(), Some("changed"));
+        assert_eq!(secret.fields["port"].as_integer(), Some(8443));
+    }
+
+    #[test]
+    fn save_keeps_pass_format_unless_converted() {
+        let secret_store = store_with_secret("bank", "s3cret\nusername: alice\n");
+        let mut modify_secret_ui = ModifySecretUI::new(&secret_store);
+
+        modify_secret_ui.open("bank");
+        save(&mut modify_secret_ui);
+        assert_eq!(secret_store.load_secrets("bank").unwrap().format, SecretFormat::Pass);
+
+        modify_secret_ui.open("bank");
+        modify_secret_ui.convert_to_toml = true;
+        save(&mut modify_secret_ui);
+        assert_eq!(secret_store.load_secrets("bank").unwrap().format, SecretFormat::Toml);
+    }
+
+    #[test]
+    fn keep_dialog_open_without_a_name() {
+        let secret_store: Rc<dyn SecretStore> = Rc::new(MemorySecretStore::new());
+        let mut modify_secret_ui = ModifySecretUI::new(&secret_store);
+        modify_secret_ui.open("");
+
+        save(&mut modify_secret_ui);
+
+        assert!(modify_secret_ui.open_dialog);
+        assert_eq!(modify_secret_ui.error_message.as_deref(), Some("Secret name cannot be empty."));
+        assert!(secret_store.load_secret_names().unwrap().is_empty());
+    }
+
+    #[test]
+    fn keep_dialog_open_when_the_store_rejects_the_secret() {
+        let secret_store = store_with_secret("bank", "password = \"s3cret\"\n");
+        let mut modify_secret_ui = ModifySecretUI::new(&secret_store);
+        modify_secret_ui.open("");
+        modify_secret_ui.updated_secret_name = "bank".to_string();
+        set_field(&mut modify_secret_ui, "password", "other");
+
+        save(&mut modify_secret_ui);
+
+        assert!(modify_secret_ui.open_dialog);
+        assert!(modify_secret_ui.error_message.as_ref().is_some_and(|error| error.starts_with("Unable to save secret")));
+        assert_eq!(secret_store.load_secrets("bank").unwrap().fields, "password = \"s3cret\"".parse::<Table>().unwrap());
+    }
+
+    #[test]
+    fn report_a_secret_that_cannot_be_loaded_instead_of_saving_it() {
+        let secret_store = store_with_secret("bank", "password = \"s3cret\"\n");
+        let mut modify_secret_ui = ModifySecretUI::new(&secret_store);
+        modify_secret_ui.open("missing");
+
+        assert!(modify_secret_ui.open_dialog);
+        assert!(modify_secret_ui.error_message.as_ref().is_some_and(|error| error.starts_with("Unable to load secret")));
+
+        save(&mut modify_secret_ui);
+        assert!(modify_secret_ui.open_dialog);
+        assert_eq!(secret_store.load_secret_names().unwrap(), vec!["bank".to_string()]);
+
+        modify_secret_ui.open("bank");
+        assert!(modify_secret_ui.error_message.is_none());
+        assert!(!modify_secret_ui.load_failed);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::passphrase_generator::{PassphraseSettings, Wordlist};
+use crate::password_generator::PasswordPolicy;
+use eframe::egui::{ComboBox, DragValue, Frame, RichText, TextEdit, Ui};
+
+const GENERATE_BUTTON_LABEL: &str = "\u{1f3b2}";
+const REGENERATE_BUTTON_LABEL: &str = "\u{1f504} Regenerate";
+const INSERT_BUTTON_LABEL: &str = "\u{2714} Insert";
+const MAX_PASSWORD_LENGTH: usize = 128;
+const MAX_WORD_COUNT: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GeneratorMode {
+    Password,
+    Passphrase,
+}
+
+/// Generates a password or passphrase for one value field at a time, with a live preview of the current rules.
+pub struct PasswordGeneratorUI {
+    policies: Vec<PasswordPolicy>,
+    wordlists: Vec<Wordlist>,
+    /// Id of the field the generator is shown below, see `ModifySecretUI::show_fields`.
+    target_field: Option<String>,
+    mode: GeneratorMode,
+    policy: PasswordPolicy,
+    passphrase_settings: PassphraseSettings,
+    wordlist_index: usize,
+    preview: Result<String, String>,
+    /// A password waiting to be written into its field.
+    inserted_password: Option<(String, String)>,
+}
+
+impl PasswordGeneratorUI {
+    /// The first policy is selected when the generator opens. Passphrases are drawn from the bundled wordlist
+    /// or one of `wordlists`.
+    pub fn new(policies: Vec<PasswordPolicy>, wordlists: Vec<Wordlist>) -> Self {
+        let policies = if policies.is_empty() { vec![PasswordPolicy::default()] } else { policies };
+        Self {
+            policy: policies[0].clone(),
+            policies,
+            wordlists: [Wordlist::bundled()].into_iter().chain(wordlists).collect(),
+            target_field: None,
+            mode: GeneratorMode::Password,
+            passphrase_settings: PassphraseSettings::default(),
+            wordlist_index: 0,
+            preview: Ok(String::new()),
+            inserted_password: None,
+        }
+    }
+
+    /// Button next to a value field that opens or closes the generator for it.
+    pub fn show_toggle_button(&mut self, ui: &mut Ui, field_id: &str) {
+        if ui.button(GENERATE_BUTTON_LABEL).on_hover_text("Generate a password or passphrase").clicked() {
+            if self.is_open_for(field_id) {
+                self.close();
+            } else {
+                self.target_field = Some(field_id.to_string());
+                self.regenerate();
+            }
+        }
+    }
+
+    pub fn is_open_for(&self, field_id: &str) -> bool {
+        self.target_field.as_deref() == Some(field_id)
+    }
+
+    pub fn close(&mut self) {
+        self.target_field = None;
+        self.preview = Ok(String::new());
+    }
+
+    /// The password the user chose to insert into `field_id`, once.
+    pub fn take_inserted_password(&mut self, field_id: &str) -> Option<String> {
+        match &self.inserted_password {
+            Some((target_field, _)) if target_field == field_id => self.inserted_password.take().map(|(_, password)| password),
+            _ => None,
+        }
+    }
+
+    fn regenerate(&mut self) {
+        let generated = match self.mode {
+            GeneratorMode::Password => self.policy.generate(),
+            GeneratorMode::Passphrase => self.passphrase_settings.generate(&self.wordlists[self.wordlist_index]),
+        };
+        self.preview = generated.map_err(|e| format!("{:#}", e));
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        let previous_settings = (self.mode, self.policy.clone(), self.passphrase_settings.clone(), self.wordlist_index);
+
+        Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.mode, GeneratorMode::Password, "Password");
+                ui.selectable_value(&mut self.mode, GeneratorMode::Passphrase, "Passphrase");
+            });
+            match self.mode {
+                GeneratorMode::Password => self.show_password_settings(ui),
+                GeneratorMode::Passphrase => self.show_passphrase_settings(ui),
+            }
+
+            ui.horizontal(|ui| {
+                match &self.preview {
+                    Ok(password) => ui.label(RichText::new(password).monospace()),
+                    Err(error) => ui.colored_label(ui.style().visuals.error_fg_color, error),
+                };
+            });
+            ui.horizontal(|ui| {
+                if ui.button(REGENERATE_BUTTON_LABEL).clicked() {
+                    self.regenerate();
+                }
+                if let (Ok(password), Some(target_field)) = (&self.preview, &self.target_field)
+                    && ui.button(INSERT_BUTTON_LABEL).clicked()
+                {
+                    self.inserted_password = Some((target_field.clone(), password.clone()));
+                    self.close();
+                }
+            });
+        });
+
+        if self.target_field.is_some() && (self.mode, self.policy.clone(), self.passphrase_settings.clone(), self.wordlist_index) != previous_settings {
+            self.regenerate();
+        }
+    }
+
+    fn show_password_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Policy:");
+            ComboBox::from_id_salt("password_policy").selected_text(&self.policy.name).show_ui(ui, |ui| {
+                for policy in &self.policies {
+                    if ui.selectable_label(self.policy.name == policy.name, &policy.name).clicked() {
+                        self.policy = policy.clone();
+                    }
+                }
+            });
+            ui.label("Length:");
+            ui.add(DragValue::new(&mut self.policy.length).range(1..=MAX_PASSWORD_LENGTH));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.policy.lowercase, "a-z");
+            ui.checkbox(&mut self.policy.uppercase, "A-Z");
+            ui.checkbox(&mut self.policy.digits, "0-9");
+            ui.checkbox(&mut self.policy.symbols, "symbols");
+            ui.checkbox(&mut self.policy.exclude_ambiguous, "no ambiguous (0O1lI|)");
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.policy.require_each_class, "at least one of each kind");
+            ui.label("must contain one of:");
+            ui.add(TextEdit::singleline(&mut self.policy.must_contain).desired_width(80.0));
+        });
+    }
+
+    fn show_passphrase_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Wordlist:");
+            ComboBox::from_id_salt("passphrase_wordlist").selected_text(&self.wordlists[self.wordlist_index].name).show_ui(ui, |ui| {
+                for (index, wordlist) in self.wordlists.iter().enumerate() {
+                    ui.selectable_value(&mut self.wordlist_index, index, format!("{} ({} words, {:.1} bits each)", wordlist.name, wordlist.len(), wordlist.bits_per_word()));
+                }
+            });
+            ui.label("Words:");
+            ui.add(DragValue::new(&mut self.passphrase_settings.word_count).range(1..=MAX_WORD_COUNT));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Separator:");
+            ui.add(TextEdit::singleline(&mut self.passphrase_settings.separator).desired_width(30.0));
+            ui.checkbox(&mut self.passphrase_settings.capitalize, "Capitalize");
+            ui.checkbox(&mut self.passphrase_settings.include_digit, "Add a digit");
+        });
+        let entropy_bits = self.passphrase_settings.entropy_bits(&self.wordlists[self.wordlist_index]);
+        ui.label(format!("Entropy: {:.1} bits", entropy_bits))
+            .on_hover_text("Assumes an attacker knows the wordlist and these settings, 80 bits or more is recommended for master passwords. Larger wordlists, such as the EFF long wordlist added through 'passphrase_wordlists', give more bits per word.");
+    }
+}
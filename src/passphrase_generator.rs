@@ -0,0 +1,225 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::password_generator::random_index;
+
+/// The BIP39 English wordlist, 2048 short and distinct words, one per line.
+pub const BUNDLED_WORDLIST: &str = include_str!("wordlists/bip39_english.txt");
+const BUNDLED_WORDLIST_NAME: &str = "BIP39 English (bundled)";
+const DIGITS: &str = "0123456789";
+
+/// Words a passphrase is drawn from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Wordlist {
+    pub name: String,
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_WORDLIST_NAME, BUNDLED_WORDLIST).expect("The bundled wordlist is valid")
+    }
+
+    /// Reads a wordlist file, named after the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).context(format!("Failed to read the wordlist {:?}", path))?;
+        let name = path.file_stem().map_or(path.to_string_lossy(), |file_stem| file_stem.to_string_lossy()).to_string();
+        Self::parse(&name, &content).context(format!("Invalid wordlist {:?}", path))
+    }
+
+    /// Accepts one word per line, or the diceware format with the dice rolls in front of each word
+    /// ("11111\tabacus") like the EFF wordlists. Duplicate words are dropped so the entropy is not overstated.
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let mut words: Vec<String> = content
+            .lines()
+            .filter_map(|line| {
+                let mut tokens = line.split_whitespace();
+                let first_token = tokens.next()?;
+                match tokens.next() {
+                    Some(word) if first_token.chars().all(|character| character.is_ascii_digit()) => Some(word.to_string()),
+                    Some(_) => None,
+                    None => Some(first_token.to_string()),
+                }
+            })
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+
+        if words.len() < 2 {
+            return Err(anyhow!("A wordlist needs at least two different words, found {}.", words.len()));
+        }
+        Ok(Self { name: name.to_string(), words })
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Entropy each word drawn from this list adds, e.g. 11 bits for 2048 words or about 12.9 for the 7776 of a
+    /// diceware list.
+    pub fn bits_per_word(&self) -> f64 {
+        (self.len() as f64).log2()
+    }
+}
+
+/// How the words of a generated passphrase are put together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassphraseSettings {
+    pub word_count: usize,
+    pub separator: String,
+    /// Starts every word with an uppercase letter.
+    pub capitalize: bool,
+    /// Appends a random digit to one random word, for sites that insist on digits.
+    pub include_digit: bool,
+}
+
+impl Default for PassphraseSettings {
+    fn default() -> Self {
+        Self {
+            word_count: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            include_digit: false,
+        }
+    }
+}
+
+impl PassphraseSettings {
+    /// Draws every word independently from the operating system's cryptographically secure random number generator.
+    pub fn generate(&self, wordlist: &Wordlist) -> Result<String> {
+        if self.word_count == 0 {
+            return Err(anyhow!("A passphrase needs at least one word."));
+        }
+
+        let mut words = (0..self.word_count)
+            .map(|_| random_index(wordlist.len()).map(|index| wordlist.words[index].clone()))
+            .collect::<Result<Vec<String>>>()?;
+        if self.capitalize {
+            words = words.iter().map(|word| Self::capitalized(word)).collect();
+        }
+        if self.include_digit {
+            let digit = DIGITS.chars().nth(random_index(DIGITS.len())?).unwrap_or('0');
+            words[random_index(self.word_count)?].push(digit);
+        }
+        Ok(words.join(&self.separator))
+    }
+
+    /// Bits of entropy of a passphrase generated with these settings, assuming the attacker knows the settings and
+    /// the wordlist. Capitalization and the separator are fixed and add nothing.
+    pub fn entropy_bits(&self, wordlist: &Wordlist) -> f64 {
+        let words_entropy = self.word_count as f64 * wordlist.bits_per_word();
+        let digit_entropy = if self.include_digit && self.word_count > 0 {
+            (DIGITS.len() as f64).log2() + (self.word_count as f64).log2()
+        } else {
+            0.0
+        };
+        words_entropy + digit_entropy
+    }
+
+    fn capitalized(word: &str) -> String {
+        let mut characters = word.chars();
+        characters.next().map_or(String::new(), |first_character| first_character.to_uppercase().chain(characters).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wordlist(words: &[&str]) -> Wordlist {
+        Wordlist::parse("test", &words.join("\n")).unwrap()
+    }
+
+    #[test]
+    fn parse_reads_diceware_lines_and_plain_words() {
+        let wordlist = Wordlist::parse("dice", "11111\tabacus\n11112 abdomen\n\nabide\n").unwrap();
+
+        assert_eq!(wordlist.name, "dice");
+        assert_eq!(wordlist.words, vec!["abacus", "abdomen", "abide"]);
+    }
+
+    #[test]
+    fn parse_skips_lines_that_are_not_words() {
+        let wordlist = Wordlist::parse("test", "# comment line\nabacus\nabdomen\n").unwrap();
+
+        assert_eq!(wordlist.words, vec!["abacus", "abdomen"]);
+    }
+
+    #[test]
+    fn parse_drops_duplicate_words() {
+        let wordlist = Wordlist::parse("test", "11111\tabacus\n11112\tabacus\nabdomen\nabdomen\n").unwrap();
+
+        assert_eq!(wordlist.len(), 2);
+    }
+
+    #[test]
+    fn parse_rejects_fewer_than_two_different_words() {
+        assert!(Wordlist::parse("test", "").is_err());
+        assert!(Wordlist::parse("test", "abacus\n").is_err());
+        assert!(Wordlist::parse("test", "abacus\n11111\tabacus\n").is_err());
+    }
+
+    #[test]
+    fn bundled_wordlist_parses() {
+        let wordlist = Wordlist::bundled();
+
+        assert_eq!(wordlist.len(), BUNDLED_WORDLIST.lines().count());
+    }
+
+    #[test]
+    fn generate_draws_the_words_from_the_wordlist() {
+        let wordlist = wordlist(&["alpha", "bravo", "charlie"]);
+        let settings = PassphraseSettings { word_count: 5, ..PassphraseSettings::default() };
+
+        let passphrase = settings.generate(&wordlist).unwrap();
+
+        let words: Vec<&str> = passphrase.split('-').collect();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|word| wordlist.words.iter().any(|known| known == word)));
+    }
+
+    #[test]
+    fn generate_capitalizes_and_appends_one_digit() {
+        let wordlist = wordlist(&["alpha", "bravo"]);
+        let settings = PassphraseSettings {
+            word_count: 4,
+            separator: " ".to_string(),
+            capitalize: true,
+            include_digit: true,
+        };
+
+        let passphrase = settings.generate(&wordlist).unwrap();
+
+        let words: Vec<&str> = passphrase.split(' ').collect();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|word| word.starts_with(['A', 'B'])));
+        assert_eq!(passphrase.chars().filter(|character| character.is_ascii_digit()).count(), 1);
+        assert!(words.iter().all(|word| !word[..word.len() - 1].contains(|character: char| character.is_ascii_digit())));
+    }
+
+    #[test]
+    fn generate_refuses_zero_words() {
+        let settings = PassphraseSettings { word_count: 0, ..PassphraseSettings::default() };
+
+        assert!(settings.generate(&wordlist(&["alpha", "bravo"])).is_err());
+    }
+
+    #[test]
+    fn entropy_bits_counts_words_and_the_digit() {
+        let wordlist = Wordlist { name: "test".to_string(), words: (0..2048).map(|index| index.to_string()).collect() };
+        let settings = PassphraseSettings::default();
+
+        assert!((settings.entropy_bits(&wordlist) - 66.0).abs() < 1e-9);
+
+        let with_digit = PassphraseSettings { word_count: 4, include_digit: true, ..settings.clone() };
+        assert!((with_digit.entropy_bits(&wordlist) - (44.0 + 10f64.log2() + 2.0)).abs() < 1e-9);
+
+        let capitalized = PassphraseSettings { capitalize: true, separator: String::new(), ..settings };
+        assert!((capitalized.entropy_bits(&wordlist) - 66.0).abs() < 1e-9);
+    }
+}
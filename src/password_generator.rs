@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+/// Characters that are easily confused with each other when read or typed.
+const AMBIGUOUS: &str = "0O1lI|`'\"";
+/// Passwords breaking a rule are drawn again, which keeps every valid password equally likely.
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// Rules a generated password follows, e.g. "bank: 16 chars, no symbols".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub name: String,
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub exclude_ambiguous: bool,
+    /// Every selected kind of character must appear at least once.
+    pub require_each_class: bool,
+    /// At least one of these characters must appear, e.g. the only symbols a site accepts. They may be used even
+    /// when their kind is not selected.
+    pub must_contain: String,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            require_each_class: true,
+            must_contain: String::new(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Draws a password from the operating system's cryptographically secure random number generator.
+    pub fn generate(&self) -> Result<String> {
+        let classes = self.character_classes();
+        let mut alphabet: Vec<char> = classes.concat();
+        alphabet.extend(self.must_contain.chars());
+        alphabet.sort_unstable();
+        alphabet.dedup();
+
+        if alphabet.is_empty() {
+            return Err(anyhow!("Select at least one kind of character."));
+        }
+        let required_characters = if self.require_each_class { classes.len() } else { 0 } + usize::from(!self.must_contain.is_empty());
+        if self.length == 0 || self.length < required_characters {
+            return Err(anyhow!("A password of {} characters cannot follow every rule, make it longer.", self.length));
+        }
+
+        for _ in 0..MAX_ATTEMPTS {
+            let password = (0..self.length).map(|_| random_index(alphabet.len()).map(|index| alphabet[index])).collect::<Result<Vec<char>>>()?;
+            if self.is_satisfied_by(&password, &classes) {
+                return Ok(password.into_iter().collect());
+            }
+        }
+        Err(anyhow!("No password following every rule was found, make it longer or relax the rules."))
+    }
+
+    fn character_classes(&self) -> Vec<Vec<char>> {
+        [(self.lowercase, LOWERCASE), (self.uppercase, UPPERCASE), (self.digits, DIGITS), (self.symbols, SYMBOLS)]
+            .into_iter()
+            .filter(|(is_selected, _)| *is_selected)
+            .map(|(_, characters)| characters.chars().filter(|character| !self.exclude_ambiguous || !AMBIGUOUS.contains(*character)).collect())
+            .collect()
+    }
+
+    fn is_satisfied_by(&self, password: &[char], classes: &[Vec<char>]) -> bool {
+        let contains_any = |characters: &[char]| password.iter().any(|character| characters.contains(character));
+        let has_each_class = !self.require_each_class || classes.iter().all(|class| contains_any(class));
+        let has_required = self.must_contain.is_empty() || contains_any(&self.must_contain.chars().collect::<Vec<char>>());
+        has_each_class && has_required
+    }
+}
+
+/// Uniform index below `bound`, rejecting the random values that would make lower indices more likely.
+pub fn random_index(bound: usize) -> Result<usize> {
+    if bound == 0 {
+        return Err(anyhow!("Cannot pick from an empty set of choices."));
+    }
+    let bound = bound as u64;
+    let accepted_range = (1u64 << 32) - (1u64 << 32) % bound;
+    loop {
+        let mut random_bytes = [0u8; 4];
+        getrandom::fill(&mut random_bytes).map_err(|e| anyhow!("The random number generator of the system failed: {}", e))?;
+        let random_value = u64::from(u32::from_le_bytes(random_bytes));
+        if random_value < accepted_range {
+            return Ok((random_value % bound) as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATED_PASSWORDS: usize = 200;
+
+    fn policy(length: usize, lowercase: bool, uppercase: bool, digits: bool, symbols: bool) -> PasswordPolicy {
+        PasswordPolicy {
+            name: "test".to_string(),
+            length,
+            lowercase,
+            uppercase,
+            digits,
+            symbols,
+            exclude_ambiguous: false,
+            require_each_class: false,
+            must_contain: String::new(),
+        }
+    }
+
+    fn generate_many(policy: &PasswordPolicy) -> Vec<String> {
+        (0..GENERATED_PASSWORDS).map(|_| policy.generate().unwrap()).collect()
+    }
+
+    #[test]
+    fn use_only_selected_characters_and_length() {
+        for password in generate_many(&policy(12, false, false, true, false)) {
+            assert_eq!(password.chars().count(), 12);
+            assert!(password.chars().all(|character| DIGITS.contains(character)), "{}", password);
+        }
+    }
+
+    #[test]
+    fn require_each_class_puts_every_kind_in_every_password() {
+        let policy = PasswordPolicy {
+            require_each_class: true,
+            ..policy(4, true, true, true, true)
+        };
+
+        for password in generate_many(&policy) {
+            for class in [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS] {
+                assert!(password.chars().any(|character| class.contains(character)), "{} misses one of {}", password, class);
+            }
+        }
+    }
+
+    #[test]
+    fn exclude_ambiguous_never_uses_ambiguous_characters() {
+        let policy = PasswordPolicy {
+            exclude_ambiguous: true,
+            ..policy(64, true, true, true, true)
+        };
+
+        for password in generate_many(&policy) {
+            assert!(!password.chars().any(|character| AMBIGUOUS.contains(character)), "{}", password);
+        }
+    }
+
+    #[test]
+    fn must_contain_puts_one_of_its_characters_in_every_password() {
+        let policy = PasswordPolicy {
+            must_contain: "!@".to_string(),
+            ..policy(8, true, false, false, false)
+        };
+
+        for password in generate_many(&policy) {
+            assert!(password.chars().any(|character| "!@".contains(character)), "{}", password);
+            assert!(password.chars().all(|character| LOWERCASE.contains(character) || "!@".contains(character)), "{}", password);
+        }
+    }
+
+    #[test]
+    fn must_contain_alone_is_enough_characters() {
+        let policy = PasswordPolicy {
+            must_contain: "xy".to_string(),
+            ..policy(6, false, false, false, false)
+        };
+
+        for password in generate_many(&policy) {
+            assert!(password.chars().all(|character| "xy".contains(character)), "{}", password);
+        }
+    }
+
+    #[test]
+    fn reject_policies_without_characters() {
+        assert!(policy(16, false, false, false, false).generate().is_err());
+    }
+
+    #[test]
+    fn reject_passwords_too_short_for_the_rules() {
+        assert!(policy(0, true, true, true, true).generate().is_err());
+
+        let policy = PasswordPolicy {
+            require_each_class: true,
+            must_contain: "!".to_string(),
+            ..policy(4, true, true, true, true)
+        };
+        assert!(policy.generate().is_err());
+        assert!(PasswordPolicy { length: 5, ..policy }.generate().is_ok());
+    }
+
+    #[test]
+    fn random_index_stays_below_its_bound() {
+        assert!(random_index(0).is_err());
+        assert_eq!(random_index(1).unwrap(), 0);
+        assert!((0..1_000).all(|_| random_index(7).unwrap() < 7));
+    }
+}
@@ -0,0 +1,661 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use toml::{Table, Value};
+
+use crate::passphrase_generator::BUNDLED_WORDLIST;
+
+/// Most common leaked passwords, the most common first.
+const COMMON_PASSWORDS: &str = include_str!("wordlists/common_passwords.txt");
+/// Guesses per character no pattern explains, as zxcvbn counts them.
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+/// Longer passwords are only analyzed this far, every further character counts as brute force.
+const MAX_ANALYZED_LENGTH: usize = 64;
+const MIN_WORD_LENGTH: usize = 3;
+const MAX_WORD_LENGTH: usize = 16;
+const MIN_KEYBOARD_PATTERN_LENGTH: usize = 4;
+const KEYBOARD_ROWS: [&str; 5] = ["`1234567890-=", "~!@#$%^&*()_+", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+const LEET_SUBSTITUTIONS: [(char, char); 9] = [('4', 'a'), ('@', 'a'), ('8', 'b'), ('3', 'e'), ('1', 'i'), ('!', 'i'), ('0', 'o'), ('$', 's'), ('5', 's')];
+/// Years an attacker tries first.
+const YEAR_GUESSES: f64 = 200.0;
+const DATE_GUESSES: f64 = 365.0 * YEAR_GUESSES;
+const DEFAULT_PASSWORD_FIELD_NAMES: [&str; 3] = ["password", "pin", "passphrase"];
+
+/// Attack speeds the crack time is estimated for, in guesses per second.
+const ATTACK_SCENARIOS: [(&str, f64); 4] = [
+    ("Online attack, throttled (100 guesses per hour)", 100.0 / 3600.0),
+    ("Online attack, unthrottled (10 guesses per second)", 10.0),
+    ("Offline attack, slow hash (10k guesses per second)", 1e4),
+    ("Offline attack, fast hash (10B guesses per second)", 1e10),
+];
+
+/// Keys of the fields whose values are rated, e.g. `password`, `wifi_password` or `PIN`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordFieldNames {
+    names: Vec<String>,
+}
+
+impl Default for PasswordFieldNames {
+    fn default() -> Self {
+        Self::new(DEFAULT_PASSWORD_FIELD_NAMES.iter().map(|name| name.to_string()).collect())
+    }
+}
+
+impl PasswordFieldNames {
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            names: names.into_iter().map(|name| name.to_lowercase()).collect(),
+        }
+    }
+
+    /// A key matches when it is one of the names, or one of its words is, ignoring case.
+    pub fn matches(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.names.contains(&key) || key.split(|character: char| !character.is_alphanumeric()).any(|word| self.names.iter().any(|name| name == word))
+    }
+
+    /// Dotted paths and values of the non-empty text fields with a matching key, including nested tables.
+    pub fn password_fields(&self, fields: &Table) -> Vec<(String, String)> {
+        fields
+            .iter()
+            .flat_map(|(key, value)| match value {
+                Value::String(text) if !text.is_empty() && self.matches(key) => vec![(key.clone(), text.clone())],
+                Value::Table(table) => self.password_fields(table).into_iter().map(|(path, text)| (format!("{}.{}", key, path), text)).collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+}
+
+/// What makes part of a password easy to guess.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pattern {
+    CommonPassword { rank: usize },
+    DictionaryWord { capitalized: bool, leet: bool, reversed: bool },
+    Repeat,
+    Sequence,
+    KeyboardRow,
+    Date,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Match {
+    start: usize,
+    end: usize,
+    guesses_log10: f64,
+    pattern: Pattern,
+}
+
+/// Offline estimate of how hard a password is to guess, following the approach of zxcvbn: the password is split
+/// into the parts an attacker would guess separately (common passwords, words, repeats, sequences, keyboard rows,
+/// dates and brute force) and the split needing the fewest guesses wins.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrengthEstimate {
+    pub guesses_log10: f64,
+    /// From 0 (too guessable) to 4 (very unguessable), like zxcvbn.
+    pub score: u8,
+    pub warning: Option<&'static str>,
+    pub suggestions: Vec<&'static str>,
+}
+
+impl StrengthEstimate {
+    pub fn of(password: &str) -> Self {
+        let characters: Vec<char> = password.chars().collect();
+        let analyzed_length = characters.len().min(MAX_ANALYZED_LENGTH);
+        let (analyzed_guesses_log10, sequence) = Self::cheapest_split(&characters[..analyzed_length]);
+        let guesses_log10 = analyzed_guesses_log10 + (characters.len() - analyzed_length) as f64 * BRUTEFORCE_CARDINALITY.log10();
+
+        let score = Self::score(guesses_log10);
+        let (warning, suggestions) = Self::feedback(score, &sequence, characters.len());
+        Self { guesses_log10, score, warning, suggestions }
+    }
+
+    /// The same thresholds as zxcvbn: fewer than 10^3 guesses is 0, then 10^6, 10^8 and 10^10.
+    fn score(guesses_log10: f64) -> u8 {
+        match guesses_log10 {
+            guesses if guesses < 3.0 => 0,
+            guesses if guesses < 6.0 => 1,
+            guesses if guesses < 8.0 => 2,
+            guesses if guesses < 10.0 => 3,
+            _ => 4,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "strong",
+            _ => "very strong",
+        }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.score < 3
+    }
+
+    /// Time needed to guess the password in each attack scenario, e.g. ("Online attack, ...", "3 hours").
+    pub fn crack_times(&self) -> Vec<(&'static str, String)> {
+        ATTACK_SCENARIOS
+            .iter()
+            .map(|(scenario, guesses_per_second)| (*scenario, Self::to_display_time(10f64.powf(self.guesses_log10) / guesses_per_second)))
+            .collect()
+    }
+
+    /// Minimum number of guesses over all ways to cover the password with matches and brute-forced characters,
+    /// with the matches of that cover.
+    fn cheapest_split(characters: &[char]) -> (f64, Vec<Match>) {
+        let matches = Self::find_matches(characters);
+        let bruteforce_log10 = BRUTEFORCE_CARDINALITY.log10();
+
+        // cheapest[end] covers characters[..end], with the match ending there if any.
+        let mut cheapest: Vec<(f64, Option<Match>)> = vec![(0.0, None)];
+        for end in 1..=characters.len() {
+            let mut best = (cheapest[end - 1].0 + bruteforce_log10, None);
+            for found in matches.iter().filter(|found| found.end == end) {
+                let guesses_log10 = cheapest[found.start].0 + found.guesses_log10;
+                if guesses_log10 < best.0 {
+                    best = (guesses_log10, Some(*found));
+                }
+            }
+            cheapest.push(best);
+        }
+
+        let mut sequence = Vec::new();
+        let mut end = characters.len();
+        while end > 0 {
+            match cheapest[end].1 {
+                Some(found) => {
+                    sequence.push(found);
+                    end = found.start;
+                }
+                None => end -= 1,
+            }
+        }
+        sequence.reverse();
+        (cheapest[characters.len()].0, sequence)
+    }
+
+    fn find_matches(characters: &[char]) -> Vec<Match> {
+        let mut matches = Self::dictionary_matches(characters);
+        matches.extend(Self::repeat_matches(characters));
+        matches.extend(Self::sequence_matches(characters));
+        matches.extend(Self::keyboard_matches(characters));
+        matches.extend(Self::date_matches(characters));
+        matches
+    }
+
+    fn dictionary_matches(characters: &[char]) -> Vec<Match> {
+        let (common_passwords, words) = dictionaries();
+        let mut matches = Vec::new();
+
+        for start in 0..characters.len() {
+            for end in (start + MIN_WORD_LENGTH)..=characters.len().min(start + MAX_WORD_LENGTH) {
+                let token = &characters[start..end];
+                let lowercase: String = token.iter().flat_map(|character| character.to_lowercase()).collect();
+                let (unleeted, substitutions) = Self::unleet(&lowercase);
+                let reversed: String = lowercase.chars().rev().collect();
+                let case_variations_log10 = Self::case_variations_log10(token);
+
+                let mut candidates = vec![(lowercase.as_str(), 0, false)];
+                if substitutions > 0 {
+                    candidates.push((unleeted.as_str(), substitutions, false));
+                }
+                if reversed != lowercase {
+                    candidates.push((reversed.as_str(), 0, true));
+                }
+                for (candidate, substitutions, reversed) in candidates {
+                    let variations_log10 = case_variations_log10 + substitutions as f64 * 2f64.log10() + if reversed { 2f64.log10() } else { 0.0 };
+                    let capitalized = case_variations_log10 > 0.0;
+                    let leet = substitutions > 0;
+
+                    if let Some(rank) = common_passwords.get(candidate) {
+                        matches.push(Match {
+                            start,
+                            end,
+                            guesses_log10: (*rank as f64).log10() + variations_log10,
+                            pattern: Pattern::CommonPassword { rank: *rank },
+                        });
+                    } else if words.contains(candidate) {
+                        matches.push(Match {
+                            start,
+                            end,
+                            guesses_log10: (words.len() as f64).log10() + variations_log10,
+                            pattern: Pattern::DictionaryWord { capitalized, leet, reversed },
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    /// Replaces look-alike digits and symbols with letters, counting the replacements.
+    fn unleet(token: &str) -> (String, usize) {
+        let mut substitutions = 0;
+        let unleeted = token
+            .chars()
+            .map(|character| match LEET_SUBSTITUTIONS.iter().find(|(leet, _)| *leet == character) {
+                Some((_, letter)) => {
+                    substitutions += 1;
+                    *letter
+                }
+                None => character,
+            })
+            .collect();
+        (unleeted, substitutions)
+    }
+
+    /// All lowercase costs nothing extra, a capital first or last letter or all capitals doubles the guesses,
+    /// anything else counts every way to place that many capitals.
+    fn case_variations_log10(token: &[char]) -> f64 {
+        let uppercase = token.iter().filter(|character| character.is_uppercase()).count();
+        let lowercase = token.iter().filter(|character| character.is_lowercase()).count();
+        if uppercase == 0 {
+            return 0.0;
+        }
+        let is_simple = lowercase == 0
+            || uppercase == 1 && (token.first().is_some_and(|character| character.is_uppercase()) || token.last().is_some_and(|character| character.is_uppercase()));
+        if is_simple {
+            return 2f64.log10();
+        }
+        let variations: f64 = (1..=uppercase.min(lowercase)).map(|count| Self::binomial(uppercase + lowercase, count)).sum();
+        variations.log10()
+    }
+
+    fn binomial(n: usize, k: usize) -> f64 {
+        (1..=k).fold(1.0, |result, i| result * (n + 1 - i) as f64 / i as f64)
+    }
+
+    /// Runs of the same character or the same chunk, like "aaaa" or "abcabc".
+    fn repeat_matches(characters: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for start in 0..characters.len() {
+            for chunk_length in 1..=(characters.len() - start) / 2 {
+                let chunk = &characters[start..start + chunk_length];
+                let count = characters[start..].chunks_exact(chunk_length).take_while(|candidate| *candidate == chunk).count();
+                if count < 2 || count * chunk_length < MIN_WORD_LENGTH {
+                    continue;
+                }
+
+                let chunk_guesses_log10 = if chunk_length == 1 { Self::cardinality(chunk[0]).log10() } else { Self::cheapest_split(chunk).0 };
+                matches.push(Match {
+                    start,
+                    end: start + count * chunk_length,
+                    guesses_log10: chunk_guesses_log10 + (count as f64).log10(),
+                    pattern: Pattern::Repeat,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Letters or digits going up or down one at a time, like "abcd" or "9876".
+    fn sequence_matches(characters: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while start + 1 < characters.len() {
+            let delta = characters[start + 1] as i64 - characters[start] as i64;
+            let mut end = start + 1;
+            while end < characters.len()
+                && characters[end] as i64 - characters[end - 1] as i64 == delta
+                && Self::cardinality(characters[end]) == Self::cardinality(characters[start])
+            {
+                end += 1;
+            }
+
+            let is_sequence_character = characters[start].is_ascii_alphanumeric();
+            if delta.abs() == 1 && end - start >= MIN_WORD_LENGTH && is_sequence_character {
+                let first_character = characters[start];
+                let base_guesses = if "aAzZ019".contains(first_character) { 4.0 } else { Self::cardinality(first_character) };
+                let direction_guesses = if delta < 0 { 2.0 } else { 1.0 };
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses_log10: (base_guesses * (end - start) as f64 * direction_guesses).log10(),
+                    pattern: Pattern::Sequence,
+                });
+            }
+            start = if end - start > 1 { end - 1 } else { end };
+        }
+        matches
+    }
+
+    /// Neighbouring keys of one row of a QWERTY keyboard, in either direction.
+    fn keyboard_matches(characters: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for start in 0..characters.len() {
+            for end in (start + MIN_KEYBOARD_PATTERN_LENGTH)..=characters.len() {
+                let token: String = characters[start..end].iter().flat_map(|character| character.to_lowercase()).collect();
+                let reversed: String = token.chars().rev().collect();
+                if !KEYBOARD_ROWS.iter().any(|row| row.contains(&token) || row.contains(&reversed)) {
+                    break;
+                }
+
+                let shifted_guesses = if characters[start..end].iter().any(|character| character.is_uppercase()) { 2.0 } else { 1.0 };
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses_log10: (KEYBOARD_ROWS.len() as f64 * 2.0 * (end - start) as f64 * shifted_guesses).log10(),
+                    pattern: Pattern::KeyboardRow,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Years from 1900 to 2099 and dates like 1990-05-12, 12.05.1990, 120590 or 12051990.
+    fn date_matches(characters: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for start in 0..characters.len() {
+            for length in [4, 6, 8, 10] {
+                let end = start + length;
+                if end > characters.len() {
+                    break;
+                }
+                let token: String = characters[start..end].iter().collect();
+                let guesses = match length {
+                    4 if Self::is_year(&token) => YEAR_GUESSES,
+                    4 => continue,
+                    _ if Self::is_date(&token) => DATE_GUESSES,
+                    _ => continue,
+                };
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses_log10: guesses.log10(),
+                    pattern: Pattern::Date,
+                });
+            }
+        }
+        matches
+    }
+
+    fn is_year(token: &str) -> bool {
+        token.len() == 4 && token.parse::<u32>().is_ok_and(|year| (1900..=2099).contains(&year))
+    }
+
+    fn is_date(token: &str) -> bool {
+        let parts: Vec<&str> = match token.chars().find(|character| !character.is_ascii_digit()) {
+            Some(separator) if "/-._ ".contains(separator) => token.split(separator).collect(),
+            Some(_) => return false,
+            None if token.len() == 6 => vec![&token[0..2], &token[2..4], &token[4..6]],
+            None if token.len() == 8 => return Self::is_date(&format!("{}-{}-{}", &token[0..2], &token[2..4], &token[4..8])) || Self::is_date(&format!("{}-{}-{}", &token[0..4], &token[4..6], &token[6..8])),
+            None => return false,
+        };
+        let [first, second, third] = parts.as_slice() else {
+            return false;
+        };
+        if ![first, second, third].iter().all(|part| !part.is_empty() && part.chars().all(|character| character.is_ascii_digit())) {
+            return false;
+        }
+
+        let is_day = |part: &str| part.len() <= 2 && part.parse::<u32>().is_ok_and(|day| (1..=31).contains(&day));
+        let is_month = |part: &str| part.len() <= 2 && part.parse::<u32>().is_ok_and(|month| (1..=12).contains(&month));
+        let is_year = |part: &str| part.len() == 2 || Self::is_year(part);
+        let day_first = is_year(third) && (is_day(first) && is_month(second) || is_month(first) && is_day(second));
+        let year_first = first.len() == 4 && Self::is_year(first) && is_month(second) && is_day(third);
+        day_first || year_first
+    }
+
+    fn cardinality(character: char) -> f64 {
+        match character {
+            'a'..='z' | 'A'..='Z' => 26.0,
+            '0'..='9' => 10.0,
+            _ => 33.0,
+        }
+    }
+
+    fn feedback(score: u8, sequence: &[Match], length: usize) -> (Option<&'static str>, Vec<&'static str>) {
+        if length == 0 {
+            return (None, vec!["Use a few words, avoid common phrases.", "No need for symbols, digits, or uppercase letters."]);
+        }
+        if score > 2 {
+            return (None, Vec::new());
+        }
+
+        let mut suggestions = vec!["Add another word or two, uncommon words are better."];
+        let Some(longest_match) = sequence.iter().max_by_key(|found| found.end - found.start) else {
+            return (None, suggestions);
+        };
+
+        let warning = match longest_match.pattern {
+            Pattern::CommonPassword { rank } if rank <= 10 => "This is a top-10 common password.",
+            Pattern::CommonPassword { rank } if rank <= 100 => "This is a top-100 common password.",
+            Pattern::CommonPassword { .. } => "This is a very common password.",
+            Pattern::DictionaryWord { capitalized, leet, reversed } => {
+                if capitalized {
+                    suggestions.push("Capitalization doesn't help very much.");
+                }
+                if leet {
+                    suggestions.push("Predictable substitutions like '@' instead of 'a' don't help very much.");
+                }
+                if reversed {
+                    suggestions.push("Reversed words aren't much harder to guess.");
+                }
+                if sequence.len() == 1 && longest_match.end - longest_match.start == length {
+                    "A word by itself is easy to guess."
+                } else {
+                    "Words from a dictionary are easy to guess."
+                }
+            }
+            Pattern::Repeat => {
+                suggestions.push("Avoid repeated words and characters.");
+                "Repeats like \"aaa\" or \"abcabc\" are easy to guess."
+            }
+            Pattern::Sequence => {
+                suggestions.push("Avoid sequences.");
+                "Sequences like \"abc\" or \"6543\" are easy to guess."
+            }
+            Pattern::KeyboardRow => {
+                suggestions.push("Avoid straight rows of keys.");
+                "Straight rows of keys are easy to guess."
+            }
+            Pattern::Date => {
+                suggestions.push("Avoid dates and years that are associated with you.");
+                "Dates and years are often easy to guess."
+            }
+        };
+        (Some(warning), suggestions)
+    }
+
+    fn to_display_time(seconds: f64) -> String {
+        const MINUTE: f64 = 60.0;
+        const HOUR: f64 = 60.0 * MINUTE;
+        const DAY: f64 = 24.0 * HOUR;
+        const MONTH: f64 = 31.0 * DAY;
+        const YEAR: f64 = 12.0 * MONTH;
+        const CENTURY: f64 = 100.0 * YEAR;
+
+        let (count, unit) = match seconds {
+            seconds if seconds < 1.0 => return "less than a second".to_string(),
+            seconds if seconds < MINUTE => (seconds, "second"),
+            seconds if seconds < HOUR => (seconds / MINUTE, "minute"),
+            seconds if seconds < DAY => (seconds / HOUR, "hour"),
+            seconds if seconds < MONTH => (seconds / DAY, "day"),
+            seconds if seconds < YEAR => (seconds / MONTH, "month"),
+            seconds if seconds < CENTURY => (seconds / YEAR, "year"),
+            _ => return "centuries".to_string(),
+        };
+        let count = count.round() as u64;
+        format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+    }
+}
+
+/// The common passwords by rank, starting at 1, and the words of the bundled wordlist.
+fn dictionaries() -> &'static (HashMap<&'static str, usize>, HashSet<&'static str>) {
+    static DICTIONARIES: OnceLock<(HashMap<&'static str, usize>, HashSet<&'static str>)> = OnceLock::new();
+    DICTIONARIES.get_or_init(|| {
+        let common_passwords = COMMON_PASSWORDS.lines().enumerate().map(|(index, password)| (password, index + 1)).collect();
+        let words = BUNDLED_WORDLIST.lines().collect();
+        (common_passwords, words)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn characters(password: &str) -> Vec<char> {
+        password.chars().collect()
+    }
+
+    fn patterns(password: &str) -> Vec<(usize, usize, Pattern)> {
+        StrengthEstimate::cheapest_split(&characters(password)).1.iter().map(|found| (found.start, found.end, found.pattern)).collect()
+    }
+
+    fn has_match(matches: &[Match], start: usize, end: usize, pattern: Pattern) -> bool {
+        matches.iter().any(|found| found.start == start && found.end == end && found.pattern == pattern)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn common_passwords_are_guessed_by_rank() {
+        let estimate = StrengthEstimate::of("password");
+
+        assert_eq!(patterns("password"), vec![(0, 8, Pattern::CommonPassword { rank: 2 })]);
+        assert_close(estimate.guesses_log10, 2f64.log10());
+        assert_eq!(estimate.score, 0);
+        assert_eq!(estimate.warning, Some("This is a top-10 common password."));
+    }
+
+    #[test]
+    fn leet_and_capitalized_common_passwords_add_few_guesses() {
+        let (guesses_log10, sequence) = StrengthEstimate::cheapest_split(&characters("P@ssw0rd"));
+
+        assert_eq!(sequence.len(), 1);
+        assert_eq!(sequence[0].pattern, Pattern::CommonPassword { rank: 2 });
+        // Rank 2, two substitutions and a capital first letter.
+        assert_close(guesses_log10, (2.0f64 * 4.0 * 2.0).log10());
+    }
+
+    #[test]
+    fn dictionary_words_are_found_leeted_and_reversed() {
+        let word_pattern = |capitalized, leet, reversed| Pattern::DictionaryWord { capitalized, leet, reversed };
+
+        assert_eq!(patterns("castle"), vec![(0, 6, word_pattern(false, false, false))]);
+        assert_eq!(patterns("Castle"), vec![(0, 6, word_pattern(true, false, false))]);
+        assert_eq!(patterns("c4stl3"), vec![(0, 6, word_pattern(false, true, false))]);
+        assert_eq!(patterns("eltsac"), vec![(0, 6, word_pattern(false, false, true))]);
+        assert_eq!(StrengthEstimate::of("castle").warning, Some("A word by itself is easy to guess."));
+        assert!(StrengthEstimate::of("c4stl3").suggestions.contains(&"Predictable substitutions like '@' instead of 'a' don't help very much."));
+    }
+
+    #[test]
+    fn dates_and_years_are_detected() {
+        assert!(has_match(&StrengthEstimate::date_matches(&characters("1987")), 0, 4, Pattern::Date));
+        for date in ["1990-05-12", "12.05.1990", "05/12/1990", "120590", "12051990", "19900512"] {
+            assert!(has_match(&StrengthEstimate::date_matches(&characters(date)), 0, date.len(), Pattern::Date), "{}", date);
+        }
+        for not_a_date in ["1899", "2100", "13131990", "1990-13-12", "12x05x1990", "32.01.1990"] {
+            assert!(!has_match(&StrengthEstimate::date_matches(&characters(not_a_date)), 0, not_a_date.len(), Pattern::Date), "{}", not_a_date);
+        }
+    }
+
+    #[test]
+    fn sequences_go_up_or_down_one_at_a_time() {
+        assert!(has_match(&StrengthEstimate::sequence_matches(&characters("abcdef")), 0, 6, Pattern::Sequence));
+        assert!(has_match(&StrengthEstimate::sequence_matches(&characters("xx9876")), 2, 6, Pattern::Sequence));
+        assert!(StrengthEstimate::sequence_matches(&characters("aceg")).is_empty());
+        assert!(StrengthEstimate::sequence_matches(&characters("ab")).is_empty());
+        assert!(StrengthEstimate::sequence_matches(&characters("!\"#$")).is_empty());
+    }
+
+    #[test]
+    fn keyboard_rows_are_detected_in_both_directions() {
+        assert!(has_match(&StrengthEstimate::keyboard_matches(&characters("asdfgh")), 0, 6, Pattern::KeyboardRow));
+        assert!(has_match(&StrengthEstimate::keyboard_matches(&characters("HGFDSA")), 0, 6, Pattern::KeyboardRow));
+        assert!(has_match(&StrengthEstimate::keyboard_matches(&characters("!@#$%")), 0, 5, Pattern::KeyboardRow));
+        assert!(StrengthEstimate::keyboard_matches(&characters("asd")).is_empty());
+        assert!(StrengthEstimate::keyboard_matches(&characters("qazwsx")).is_empty());
+    }
+
+    #[test]
+    fn repeats_of_characters_and_chunks_are_detected() {
+        assert!(has_match(&StrengthEstimate::repeat_matches(&characters("aaaa")), 0, 4, Pattern::Repeat));
+        assert!(has_match(&StrengthEstimate::repeat_matches(&characters("xyzxyz")), 0, 6, Pattern::Repeat));
+        assert!(StrengthEstimate::repeat_matches(&characters("aa")).is_empty());
+        assert_eq!(patterns("zzzzzzzz"), vec![(0, 8, Pattern::Repeat)]);
+    }
+
+    #[test]
+    fn cheapest_split_combines_matches_and_brute_force() {
+        assert_eq!(StrengthEstimate::cheapest_split(&[]).0, 0.0);
+
+        let (guesses_log10, sequence) = StrengthEstimate::cheapest_split(&characters("x"));
+        assert!(sequence.is_empty());
+        assert_close(guesses_log10, BRUTEFORCE_CARDINALITY.log10());
+
+        let (guesses_log10, sequence) = StrengthEstimate::cheapest_split(&characters("castle1987"));
+        assert_eq!(sequence.iter().map(|found| (found.start, found.end)).collect::<Vec<_>>(), vec![(0, 6), (6, 10)]);
+        assert_eq!(sequence[1].pattern, Pattern::Date);
+        assert_close(guesses_log10, sequence[0].guesses_log10 + YEAR_GUESSES.log10());
+
+        let (guesses_log10, sequence) = StrengthEstimate::cheapest_split(&characters("castle#"));
+        assert_eq!(sequence.len(), 1);
+        assert_close(guesses_log10, sequence[0].guesses_log10 + BRUTEFORCE_CARDINALITY.log10());
+    }
+
+    #[test]
+    fn score_thresholds() {
+        assert_eq!(StrengthEstimate::score(0.0), 0);
+        assert_eq!(StrengthEstimate::score(2.99), 0);
+        assert_eq!(StrengthEstimate::score(3.0), 1);
+        assert_eq!(StrengthEstimate::score(5.99), 1);
+        assert_eq!(StrengthEstimate::score(6.0), 2);
+        assert_eq!(StrengthEstimate::score(8.0), 3);
+        assert_eq!(StrengthEstimate::score(9.99), 3);
+        assert_eq!(StrengthEstimate::score(10.0), 4);
+
+        assert!(StrengthEstimate::of("").is_weak());
+        assert!(!StrengthEstimate::of("vU8#qL2!mZr$0pXw").is_weak());
+    }
+
+    #[test]
+    fn password_field_names_match_whole_words_ignoring_case() {
+        let names = PasswordFieldNames::default();
+
+        assert!(names.matches("password"));
+        assert!(names.matches("Wifi_Password"));
+        assert!(names.matches("PIN"));
+        assert!(names.matches("bank-pin"));
+        assert!(!names.matches("spinner"));
+        assert!(!names.matches("passwords"));
+        assert!(!names.matches("username"));
+        assert!(PasswordFieldNames::new(vec!["Secret".to_string()]).matches("api secret"));
+    }
+
+    #[test]
+    fn password_fields_include_nested_tables_and_skip_empty_values() {
+        let fields: Table = toml::from_str(
+            r#"
+            username = "alice"
+            password = "s3cret"
+            recovery_pin = ""
+            pin = 1234
+
+            [wifi]
+            wifi_password = "hunter2"
+
+            [wifi.guest]
+            passphrase = "correct horse"
+            "#,
+        )
+        .unwrap();
+
+        let mut password_fields = PasswordFieldNames::default().password_fields(&fields);
+        password_fields.sort();
+
+        assert_eq!(
+            password_fields,
+            vec![
+                ("password".to_string(), "s3cret".to_string()),
+                ("wifi.guest.passphrase".to_string(), "correct horse".to_string()),
+                ("wifi.wifi_password".to_string(), "hunter2".to_string()),
+            ]
+        );
+    }
+}
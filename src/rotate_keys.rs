@@ -0,0 +1,168 @@
+use std::rc::Rc;
+
+use crate::secret_store::{ReencryptedSecret, SecretStore};
+use eframe::egui::{Align, Button, CentralPanel, Id, Layout, Panel, ProgressBar, ScrollArea, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const ROTATE_KEYS_TITLE: &str = "Rotate Keys";
+const CLOSE_BUTTON_LABEL: &str = "\u{274c} Close";
+const START_BUTTON_LABEL: &str = "\u{1f511} Re-encrypt";
+
+pub struct RotateKeysUI {
+    secret_store: Rc<dyn SecretStore>,
+    open_dialog: bool,
+    is_running: bool,
+    pending_secrets: Vec<String>,
+    total_secrets: usize,
+    reencrypted_secrets: Vec<ReencryptedSecret>,
+    failures: Vec<(String, String)>,
+    result_message: Option<String>,
+    error_message: Option<String>,
+}
+
+impl RotateKeysUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            open_dialog: false,
+            is_running: false,
+            pending_secrets: Vec::new(),
+            total_secrets: 0,
+            reencrypted_secrets: Vec::new(),
+            failures: Vec::new(),
+            result_message: None,
+            error_message: None,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.reset();
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.reset();
+        self.open_dialog = false;
+    }
+
+    fn reset(&mut self) {
+        self.is_running = false;
+        self.pending_secrets = Vec::new();
+        self.total_secrets = 0;
+        self.reencrypted_secrets = Vec::new();
+        self.failures = Vec::new();
+        self.result_message = None;
+        self.error_message = None;
+    }
+
+    fn start(&mut self) {
+        self.reset();
+        match self.secret_store.load_secret_names() {
+            Ok(secret_names) if secret_names.is_empty() => {
+                self.result_message = Some("There are no secrets to re-encrypt.".to_string());
+            }
+            Ok(mut secret_names) => {
+                secret_names.reverse();
+                self.total_secrets = secret_names.len();
+                self.pending_secrets = secret_names;
+                self.is_running = true;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to list secrets: {}", e));
+            }
+        }
+    }
+
+    /// Re-encrypts one secret per frame so the progress bar keeps moving, then writes everything at once.
+    fn process_next_secret(&mut self, ui: &Ui) {
+        if let Some(secret_name) = self.pending_secrets.pop() {
+            match self.secret_store.reencrypt_secret(&secret_name) {
+                Ok(reencrypted) => self.reencrypted_secrets.push(reencrypted),
+                Err(e) => {
+                    log::error!("Failed to re-encrypt secret '{}': {:#?}", secret_name, e);
+                    self.failures.push((secret_name, format!("{:#}", e)));
+                }
+            }
+            ui.ctx().request_repaint();
+            return;
+        }
+
+        self.is_running = false;
+        if !self.failures.is_empty() {
+            self.error_message = Some(format!("{} secret(s) could not be re-encrypted, the vault was left unchanged.", self.failures.len()));
+            return;
+        }
+
+        match self.secret_store.apply_reencryption(&self.reencrypted_secrets) {
+            Ok(_) => {
+                self.result_message = Some(format!("Re-encrypted {} secret(s).", self.reencrypted_secrets.len()));
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to re-encrypt the vault: {:#}", e));
+            }
+        }
+        self.reencrypted_secrets = Vec::new();
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let rotate_keys_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(520.0, 320.0))
+                .with_title(ROTATE_KEYS_TITLE)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("rotate_keys_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, rotate_keys_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) && !self.is_running {
+                    self.close();
+                }
+
+                if self.is_running {
+                    self.process_next_secret(ui);
+                }
+
+                Panel::bottom(Id::new("rotate_keys_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.add_enabled(!self.is_running, Button::new(CLOSE_BUTTON_LABEL)).clicked() {
+                                self.close();
+                            }
+                            if ui.add_enabled(!self.is_running, Button::new(START_BUTTON_LABEL)).clicked() {
+                                self.start();
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    ui.label("Decrypt every secret and encrypt it again to the currently configured recipients.");
+                    ui.label("Nothing is written unless every secret can be re-encrypted.");
+                    ui.add_space(6.0);
+
+                    if self.total_secrets > 0 {
+                        let processed_secrets = self.total_secrets - self.pending_secrets.len();
+                        ui.add(
+                            ProgressBar::new(processed_secrets as f32 / self.total_secrets as f32)
+                                .text(format!("{} / {}", processed_secrets, self.total_secrets)),
+                        );
+                    }
+
+                    if let Some(result) = &self.result_message {
+                        ui.label(result);
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+
+                    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                        self.failures.iter().for_each(|(secret_name, error)| {
+                            ui.label(format!("{}: {}", secret_name, error));
+                        });
+                    });
+                });
+            });
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use anyhow::{anyhow, Result};
+use toml::{Table, Value};
+use toml_writer::{ToTomlKey, ToTomlValue, TomlStringBuilder};
+
+const PASSWORD_KEY: &str = "password";
+const TOTP_URL_KEY: &str = "totpurl";
+const NOTES_KEY: &str = "notes";
+const OTPAUTH_PREFIX: &str = "otpauth://";
+
+/// Plaintext layout of a decrypted secret file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecretFormat {
+    /// Rustillium's own `key = "value"` TOML document.
+    #[default]
+    Toml,
+    /// The `pass` layout: the password on the first line followed by `key: value` lines.
+    Pass,
+}
+
+/// Outcome of checking the signature of a secret file against the trusted signers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Signed by a trusted signer, named by e-mail or fingerprint.
+    Valid(String),
+    /// Correctly signed, but by a key that is missing from the keyring or not trusted.
+    UnknownSigner(String),
+    Unsigned,
+    /// The signature does not match the content or could not be checked.
+    Bad,
+}
+
+/// Decrypted secret fields, with their TOML types and nesting, together with the format they were stored in.
+#[derive(Clone, Debug, Default)]
+pub struct Secret {
+    pub fields: Table,
+    pub format: SecretFormat,
+    /// `None` when the storage backend does not sign secrets.
+    pub signature: Option<SignatureStatus>,
+    /// Key of every line after the password of a `pass` entry, in file order, so saving keeps the lines where they
+    /// were. Free-form lines count as [`NOTES_KEY`].
+    pass_layout: Vec<String>,
+}
+
+impl Secret {
+    pub fn new(fields: Table, format: SecretFormat) -> Self {
+        Self { fields, format, signature: None, pass_layout: Vec::new() }
+    }
+
+    /// The same secret with other fields, keeping the line order of a `pass` entry for the fields that are left.
+    pub fn with_fields(&self, fields: Table) -> Self {
+        Self { fields, format: self.format, signature: None, pass_layout: self.pass_layout.clone() }
+    }
+
+    /// Parses decrypted content. A `pass` entry starts with a bare password, so content is only TOML when its first
+    /// line is a table header, a comment or a `key = value` pair and the whole document is a non-empty table. An
+    /// empty file is a `pass` entry with an empty password.
+    pub fn parse(content: &str) -> Result<Self> {
+        let first_line = content.lines().next().unwrap_or_default().trim();
+        let looks_like_toml = first_line.starts_with('[') || first_line.starts_with('#') || first_line.contains(" = ");
+        match content.parse::<Table>() {
+            Ok(fields) if looks_like_toml && !fields.is_empty() => Ok(Self::new(fields, SecretFormat::Toml)),
+            _ => Ok(Self::parse_pass(content)),
+        }
+    }
+
+    pub fn serialize(&self) -> Result<String> {
+        match self.format {
+            SecretFormat::Toml => Ok(Self::serialize_toml(&self.fields)),
+            SecretFormat::Pass => self.serialize_pass(),
+        }
+    }
+
+    /// Writes the fields as a TOML document, tables after the plain fields like `toml` does. Text with line breaks
+    /// is always written as a multi-line string, so notes stay readable in the decrypted file.
+    fn serialize_toml(fields: &Table) -> String {
+        let mut document = String::new();
+        Self::write_toml_table(&mut document, "", fields);
+        document.trim_start_matches('\n').to_string()
+    }
+
+    fn write_toml_table(document: &mut String, path: &str, table: &Table) {
+        let is_array_of_tables = |value: &Value| matches!(value, Value::Array(array) if !array.is_empty() && array.iter().all(Value::is_table));
+        for (key, value) in table.iter().filter(|(_, value)| !value.is_table() && !is_array_of_tables(value)) {
+            document.push_str(&format!("{} = {}\n", key.as_str().to_toml_key(), Self::toml_value(value)));
+        }
+
+        for (key, value) in table {
+            let section_path = if path.is_empty() { key.as_str().to_toml_key() } else { format!("{}.{}", path, key.as_str().to_toml_key()) };
+            match value {
+                Value::Table(section) => {
+                    document.push_str(&format!("\n[{}]\n", section_path));
+                    Self::write_toml_table(document, &section_path, section);
+                }
+                Value::Array(array) if is_array_of_tables(value) => {
+                    for section in array.iter().filter_map(Value::as_table) {
+                        document.push_str(&format!("\n[[{}]]\n", section_path));
+                        Self::write_toml_table(document, &section_path, section);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Multi-line text becomes a `'''` literal string when it has nothing to escape, a `"""` basic string otherwise.
+    fn toml_value(value: &Value) -> String {
+        match value {
+            Value::String(text) if text.contains('\n') => {
+                let builder = TomlStringBuilder::new(text);
+                builder.as_ml_literal().unwrap_or_else(|| builder.as_ml_basic()).to_toml_value()
+            }
+            Value::String(text) => TomlStringBuilder::new(text).as_default().to_toml_value(),
+            Value::Array(array) => format!("[{}]", array.iter().map(Self::toml_value).collect::<Vec<String>>().join(", ")),
+            Value::Table(table) => {
+                let fields: Vec<String> = table.iter().map(|(key, value)| format!("{} = {}", key.as_str().to_toml_key(), Self::toml_value(value))).collect();
+                if fields.is_empty() { "{}".to_string() } else { format!("{{ {} }}", fields.join(", ")) }
+            }
+            scalar => scalar.to_string(),
+        }
+    }
+
+    fn parse_pass(content: &str) -> Self {
+        let mut lines = content.lines();
+        let mut fields = Table::new();
+        let mut pass_layout = Vec::new();
+        let mut notes: Vec<&str> = Vec::new();
+
+        fields.insert(PASSWORD_KEY.to_string(), Value::from(lines.next().unwrap_or_default()));
+
+        for line in lines {
+            if line.starts_with(OTPAUTH_PREFIX) && !fields.contains_key(TOTP_URL_KEY) {
+                fields.insert(TOTP_URL_KEY.to_string(), Value::from(line));
+                pass_layout.push(TOTP_URL_KEY.to_string());
+                continue;
+            }
+
+            match line.split_once(": ") {
+                Some((key, value)) if !key.trim().is_empty() && key != NOTES_KEY && !fields.contains_key(key) => {
+                    fields.insert(key.to_string(), Value::from(value));
+                    pass_layout.push(key.to_string());
+                }
+                _ => {
+                    notes.push(line);
+                    pass_layout.push(NOTES_KEY.to_string());
+                }
+            }
+        }
+
+        if !notes.is_empty() {
+            fields.insert(NOTES_KEY.to_string(), Value::from(notes.join("\n")));
+        }
+        Self { pass_layout, ..Self::new(fields, SecretFormat::Pass) }
+    }
+
+    /// Plain text of a scalar field as written in a `pass` entry; arrays and tables have none.
+    fn to_pass_text(key: &str, value: &Value) -> Result<String> {
+        match value {
+            Value::String(text) => Ok(text.clone()),
+            Value::Array(_) | Value::Table(_) => Err(anyhow!("Field '{}' is a list or table, which the pass format cannot store, convert the secret to TOML instead.", key)),
+            scalar => Ok(scalar.to_string()),
+        }
+    }
+
+    /// Writes the lines in the order they were parsed in. Fields added since follow sorted by key, then the TOTP URL
+    /// and the notes lines that no longer fit in their original places.
+    fn serialize_pass(&self) -> Result<String> {
+        let text_of = |key: &str| self.fields.get(key).map(|value| Self::to_pass_text(key, value)).transpose();
+        let password = text_of(PASSWORD_KEY)?.unwrap_or_default();
+        if password.contains('\n') {
+            return Err(anyhow!("The password cannot span several lines in the pass format, convert the secret to TOML instead."));
+        }
+        let notes = text_of(NOTES_KEY)?;
+        let mut notes_lines = notes.as_deref().map(|notes| notes.split('\n')).into_iter().flatten();
+
+        let mut added_keys: Vec<&String> = self
+            .fields
+            .keys()
+            .filter(|key| ![PASSWORD_KEY, TOTP_URL_KEY, NOTES_KEY].contains(&key.as_str()) && !self.pass_layout.contains(key))
+            .collect();
+        added_keys.sort();
+        let mut keys: Vec<&str> = self.pass_layout.iter().map(String::as_str).chain(added_keys.iter().map(|key| key.as_str())).collect();
+        keys.push(TOTP_URL_KEY);
+
+        let mut lines = vec![password];
+        let mut written_keys: Vec<&str> = Vec::new();
+        for key in keys {
+            if key == NOTES_KEY {
+                lines.extend(notes_lines.next().map(str::to_string));
+                continue;
+            }
+            if written_keys.contains(&key) {
+                continue;
+            }
+            let Some(value) = text_of(key)? else {
+                continue;
+            };
+            written_keys.push(key);
+
+            if key == TOTP_URL_KEY && value.starts_with(OTPAUTH_PREFIX) {
+                lines.push(value);
+                continue;
+            }
+            if key.contains('\n') || value.contains('\n') || key.contains(": ") {
+                return Err(anyhow!("Field '{}' cannot be stored in the pass format, convert the secret to TOML instead.", key));
+            }
+            lines.push(format!("{}: {}", key, value));
+        }
+        lines.extend(notes_lines.map(str::to_string));
+
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pass_with_only_a_password() {
+        let secret = Secret::parse("correct horse battery staple\n").unwrap();
+
+        assert_eq!(secret.format, SecretFormat::Pass);
+        assert_eq!(secret.fields.len(), 1);
+        assert_eq!(secret.fields[PASSWORD_KEY].as_str(), Some("correct horse battery staple"));
+    }
+
+    #[test]
+    fn parse_pass_with_key_value_lines() {
+        let secret = Secret::parse("s3cret\nusername: alice\nurl: https://example.com/login\notpauth://totp/Example?secret=JBSWY3DPEHPK3PXP\n").unwrap();
+
+        assert_eq!(secret.format, SecretFormat::Pass);
+        assert_eq!(secret.fields[PASSWORD_KEY].as_str(), Some("s3cret"));
+        assert_eq!(secret.fields["username"].as_str(), Some("alice"));
+        assert_eq!(secret.fields["url"].as_str(), Some("https://example.com/login"));
+        assert_eq!(secret.fields[TOTP_URL_KEY].as_str(), Some("otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP"));
+        assert!(!secret.fields.contains_key(NOTES_KEY));
+    }
+
+    #[test]
+    fn parse_pass_keeps_free_form_lines_as_notes() {
+        let secret = Secret::parse("s3cret\nusername: alice\nrecovery codes below\n1234-5678\nusername: bob\n").unwrap();
+
+        assert_eq!(secret.fields["username"].as_str(), Some("alice"));
+        assert_eq!(secret.fields[NOTES_KEY].as_str(), Some("recovery codes below\n1234-5678\nusername: bob"));
+    }
+
+    #[test]
+    fn parse_keeps_toml_documents_as_toml() {
+        let secret = Secret::parse("password = \"s3cret\"\n").unwrap();
+
+        assert_eq!(secret.format, SecretFormat::Toml);
+        assert_eq!(secret.fields[PASSWORD_KEY].as_str(), Some("s3cret"));
+    }
+
+    #[test]
+    fn serialize_pass_round_trip_is_stable() {
+        let content = "s3cret\nurl: https://example.com\nusername: alice\notpauth://totp/Example?secret=JBSWY3DPEHPK3PXP\nfree form note\nsecond line\n";
+
+        let serialized = Secret::parse(content).unwrap().serialize().unwrap();
+        assert_eq!(serialized, content);
+
+        let reserialized = Secret::parse(&serialized).unwrap().serialize().unwrap();
+        assert_eq!(reserialized, serialized);
+    }
+
+    #[test]
+    fn parse_treats_a_bare_password_that_is_valid_toml_as_pass() {
+        let secret = Secret::parse("a=1\n").unwrap();
+
+        assert_eq!(secret.format, SecretFormat::Pass);
+        assert_eq!(secret.fields[PASSWORD_KEY].as_str(), Some("a=1"));
+    }
+
+    #[test]
+    fn parse_treats_an_empty_file_as_pass() {
+        let secret = Secret::parse("").unwrap();
+
+        assert_eq!(secret.format, SecretFormat::Pass);
+        assert_eq!(secret.fields[PASSWORD_KEY].as_str(), Some(""));
+    }
+
+    #[test]
+    fn parse_accepts_toml_starting_with_a_table_or_comment() {
+        assert_eq!(Secret::parse("[server]\nhost = \"example.com\"\n").unwrap().format, SecretFormat::Toml);
+        assert_eq!(Secret::parse("# bank\npassword = \"s3cret\"\n").unwrap().format, SecretFormat::Toml);
+        assert_eq!(Secret::parse("# only a comment\n").unwrap().format, SecretFormat::Pass);
+    }
+
+    #[test]
+    fn serialize_pass_keeps_the_line_order() {
+        let content = "s3cret\nnote first\nzone: b\notpauth://totp/Example?secret=JBSWY3DPEHPK3PXP\nalpha: a\nnote last\n";
+
+        assert_eq!(Secret::parse(content).unwrap().serialize().unwrap(), content);
+    }
+
+    #[test]
+    fn serialize_pass_keeps_the_line_order_of_edited_fields() {
+        let secret = Secret::parse("s3cret\nzone: b\nnote\nalpha: a\n").unwrap();
+        let mut fields = secret.fields.clone();
+        fields.remove("zone");
+        fields.insert("alpha".to_string(), Value::from("changed"));
+        fields.insert("url".to_string(), Value::from("https://example.com"));
+        fields.insert("beta".to_string(), Value::from("new"));
+        fields.insert(NOTES_KEY.to_string(), Value::from("note\nadded note"));
+
+        assert_eq!(secret.with_fields(fields).serialize().unwrap(), "s3cret\nnote\nalpha: changed\nbeta: new\nurl: https://example.com\nadded note\n");
+    }
+
+    #[test]
+    fn serialize_new_pass_entry_sorts_keys_and_puts_notes_last() {
+        let mut fields = Table::new();
+        fields.insert(PASSWORD_KEY.to_string(), Value::from("s3cret"));
+        fields.insert(NOTES_KEY.to_string(), Value::from("note"));
+        fields.insert("zone".to_string(), Value::from("b"));
+        fields.insert("alpha".to_string(), Value::from("a"));
+
+        assert_eq!(Secret::new(fields, SecretFormat::Pass).serialize().unwrap(), "s3cret\nalpha: a\nzone: b\nnote\n");
+    }
+
+    #[test]
+    fn serialize_pass_rejects_tables_and_multiline_values() {
+        let mut fields = Table::new();
+        fields.insert(PASSWORD_KEY.to_string(), Value::from("s3cret"));
+        fields.insert("server".to_string(), Value::Table(Table::new()));
+        assert!(Secret::new(fields, SecretFormat::Pass).serialize().is_err());
+
+        let mut fields = Table::new();
+        fields.insert(PASSWORD_KEY.to_string(), Value::from("s3cret"));
+        fields.insert("address".to_string(), Value::from("line one\nline two"));
+        assert!(Secret::new(fields, SecretFormat::Pass).serialize().is_err());
+    }
+
+    fn round_trip(fields: &Table) -> String {
+        let serialized = Secret::new(fields.clone(), SecretFormat::Toml).serialize().unwrap();
+        let parsed = Secret::parse(&serialized).unwrap();
+        assert_eq!(parsed.format, SecretFormat::Toml);
+        assert_eq!(&parsed.fields, fields, "{}", serialized);
+        serialized
+    }
+
+    #[test]
+    fn serialize_toml_writes_multi_line_values_as_literal_strings() {
+        let mut fields = Table::new();
+        fields.insert(PASSWORD_KEY.to_string(), Value::from("s3cret"));
+        fields.insert(NOTES_KEY.to_string(), Value::from("first line\n  indented \"second\" line\n\nafter a blank line\n"));
+
+        let serialized = round_trip(&fields);
+
+        assert!(serialized.contains("notes = '''\nfirst line\n  indented \"second\" line\n\nafter a blank line\n'''\n"), "{}", serialized);
+        assert!(serialized.contains("password = \"s3cret\"\n"), "{}", serialized);
+    }
+
+    #[test]
+    fn serialize_toml_escapes_multi_line_values_that_a_literal_string_cannot_hold() {
+        let mut fields = Table::new();
+        fields.insert("quotes".to_string(), Value::from("it'''s\nquoted"));
+        fields.insert("windows".to_string(), Value::from("crlf\r\nline\twith tab\\"));
+
+        let serialized = round_trip(&fields);
+
+        assert!(serialized.contains("quotes = \"\"\"\n"), "{}", serialized);
+        assert!(serialized.contains("windows = \"\"\"\n"), "{}", serialized);
+    }
+
+    #[test]
+    fn serialize_toml_keeps_nested_tables_and_arrays() {
+        let fields: Table = r#"
+            password = "s3cret"
+            ports = [80, 443]
+            "odd key" = true
+
+            [server]
+            host = "example.com"
+            notes = """
+            multi
+            line"""
+
+            [server.backup]
+            host = "backup.example.com"
+
+            [[keys]]
+            name = "first"
+            lines = ["a\nb", "c"]
+
+            [[keys]]
+            name = "second"
+            inline = { answer = 42 }
+        "#
+        .parse()
+        .unwrap();
+
+        let serialized = round_trip(&fields);
+
+        assert!(serialized.contains("[server.backup]\n"), "{}", serialized);
+        assert_eq!(serialized.matches("[[keys]]\n").count(), 2, "{}", serialized);
+    }
+}
@@ -0,0 +1,246 @@
+use std::rc::Rc;
+
+use crate::cache::forget_secret;
+use crate::secret::SignatureStatus;
+use crate::secret_store::{SecretRevision, SecretStore};
+use eframe::egui::{
+    Align, Button, CentralPanel, Color32, Grid, Id, Layout, Panel, RichText, ScrollArea, Ui, Vec2, ViewportBuilder, ViewportId,
+};
+use toml::{Table, Value};
+
+const HISTORY_TITLE: &str = "History";
+const CLOSE_BUTTON_LABEL: &str = "\u{274c} Close";
+const RESTORE_BUTTON_LABEL: &str = "\u{21ba} Restore This Version";
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Lists the revisions of a secret, shows a past version read-only and restores it as a new change.
+pub struct SecretHistoryUI {
+    secret_store: Rc<dyn SecretStore>,
+    secret_name: String,
+    revisions: Vec<SecretRevision>,
+    selected_revision: Option<usize>,
+    /// Fields of the selected revision, or why it cannot be shown.
+    selected_fields: Result<Vec<(String, String)>, String>,
+    open_dialog: bool,
+    result_message: Option<String>,
+    error_message: Option<String>,
+}
+
+impl SecretHistoryUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            secret_name: String::new(),
+            revisions: Vec::new(),
+            selected_revision: None,
+            selected_fields: Ok(Vec::new()),
+            open_dialog: false,
+            result_message: None,
+            error_message: None,
+        }
+    }
+
+    pub fn open(&mut self, secret_name: &str) {
+        self.secret_name = secret_name.to_string();
+        self.result_message = None;
+        self.load_history();
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.secret_name = String::new();
+        self.revisions = Vec::new();
+        self.selected_revision = None;
+        self.selected_fields = Ok(Vec::new());
+        self.result_message = None;
+        self.error_message = None;
+    }
+
+    fn load_history(&mut self) {
+        self.selected_revision = None;
+        self.selected_fields = Ok(Vec::new());
+        match self.secret_store.history(&self.secret_name) {
+            Ok(revisions) => {
+                self.revisions = revisions;
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.revisions = Vec::new();
+                self.error_message = Some(format!("Unable to load the history: {:#}", e));
+            }
+        }
+    }
+
+    fn select_revision(&mut self, index: usize) {
+        self.selected_revision = Some(index);
+        self.result_message = None;
+        self.selected_fields = self
+            .secret_store
+            .load_secret_revision(&self.revisions[index])
+            .map(|secret| to_field_rows(&secret.fields))
+            .map_err(|e| format!("{:#}", e));
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let history_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(760.0, 420.0))
+                .with_title(format!("{}: {}", HISTORY_TITLE, self.secret_name))
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("secret_history_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, history_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("history_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CLOSE_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            let can_restore = self.selected_revision.is_some() && self.selected_fields.is_ok();
+                            if ui.add_enabled(can_restore, Button::new(RESTORE_BUTTON_LABEL)).clicked() {
+                                self.handle_restore(ui);
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                Panel::left(Id::new("history_revisions_panel")).resizable(true).default_size(340.0).show_inside(ui, |ui| {
+                    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                        let mut clicked_revision = None;
+                        for (index, revision) in self.revisions.iter().enumerate() {
+                            let label = match &revision.author {
+                                Some(author) => format!("{}  {} \u{2014} {}", format_time(revision.time), revision.message, author),
+                                None => format!("{}  {}", format_time(revision.time), revision.message),
+                            };
+                            if ui.selectable_label(self.selected_revision == Some(index), label).clicked() {
+                                clicked_revision = Some(index);
+                            }
+                        }
+                        if let Some(index) = clicked_revision {
+                            self.select_revision(index);
+                        }
+                    });
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    if let Some(result) = &self.result_message {
+                        ui.label(result);
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+
+                    if let Some(revision) = self.selected_revision.map(|index| &self.revisions[index]) {
+                        Self::build_revision_details(revision, ui);
+                        ui.add_space(6.0);
+                    }
+
+                    match (&self.selected_fields, self.selected_revision) {
+                        (_, None) => {
+                            ui.label("Select a version to view it.");
+                        }
+                        (Ok(fields), Some(_)) => show_field_rows(fields, ui),
+                        (Err(error), Some(_)) => {
+                            ui.colored_label(ui.style().visuals.error_fg_color, error);
+                        }
+                    }
+                });
+            });
+        }
+    }
+
+    fn build_revision_details(revision: &SecretRevision, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if let Some(author) = &revision.author {
+                ui.label(format!("Changed by {}", author));
+            }
+            let (badge, color, tooltip) = match &revision.commit_signature {
+                Some(SignatureStatus::Valid(signer)) => ("\u{2714} signed", Color32::from_rgb(60, 160, 60), format!("Change signed by {}", signer)),
+                Some(SignatureStatus::UnknownSigner(fingerprint)) => {
+                    ("\u{2754} unknown signer", Color32::from_rgb(200, 150, 30), format!("Change signed by an untrusted or unknown key {}", fingerprint))
+                }
+                Some(SignatureStatus::Unsigned) => ("unsigned", ui.style().visuals.weak_text_color(), "This change is not signed".to_string()),
+                Some(SignatureStatus::Bad) => ("\u{26a0} bad signature", ui.style().visuals.error_fg_color, "The signature of this change does not match".to_string()),
+                None => return,
+            };
+            ui.label(RichText::new(badge).small().color(color)).on_hover_text(tooltip);
+        });
+    }
+
+    fn handle_restore(&mut self, ui: &mut Ui) {
+        let Some(revision) = self.selected_revision.map(|index| self.revisions[index].clone()) else {
+            return;
+        };
+
+        match self.secret_store.restore_secret(&self.secret_name, &revision) {
+            Ok(_) => {
+                Self::clear_ui_cache(ui, &self.secret_name);
+                self.load_history();
+                self.result_message = Some(format!("Restored the version from {}.", format_time(revision.time)));
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to restore the secret: {:#}", e));
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui, secret_name: &str) {
+        ui.memory_mut(|m| forget_secret(&mut m.data, secret_name));
+    }
+}
+
+/// Flattens a secret into rows of dotted field names and their text, for read-only views.
+pub fn to_field_rows(table: &Table) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+    collect_field_rows("", table, &mut rows);
+    rows
+}
+
+fn collect_field_rows(prefix: &str, table: &Table, rows: &mut Vec<(String, String)>) {
+    for (key, value) in table {
+        let field_name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::Table(nested_table) => collect_field_rows(&field_name, nested_table, rows),
+            Value::String(text) => rows.push((field_name, text.clone())),
+            other => rows.push((field_name, other.to_string())),
+        }
+    }
+}
+
+pub fn show_field_rows(fields: &[(String, String)], ui: &mut Ui) {
+    ScrollArea::vertical().id_salt("field_rows").auto_shrink(false).show(ui, |ui| {
+        Grid::new("field_rows_grid").num_columns(2).striped(true).spacing([16.0, 6.0]).show(ui, |ui| {
+            for (field_name, value) in fields {
+                ui.label(RichText::new(field_name).strong());
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM` in UTC.
+pub fn format_time(time: i64) -> String {
+    let (days, seconds_of_day) = (time.div_euclid(SECONDS_PER_DAY), time.rem_euclid(SECONDS_PER_DAY));
+
+    // Converts days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let shifted_days = days + 719_468;
+    let era = shifted_days.div_euclid(146_097);
+    let day_of_era = shifted_days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds_of_day / 3_600, seconds_of_day % 3_600 / 60)
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeSet;
+
+use toml::{Table, Value};
+
+/// A field both sides changed differently since their common version. `None` means the side removed the field.
+#[derive(Clone, Debug)]
+pub struct FieldConflict {
+    /// Keys from the top-level table down to the conflicting field.
+    pub field_path: Vec<String>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+impl FieldConflict {
+    /// Dotted path as shown to the user, e.g. `replica.port`.
+    pub fn field_name(&self) -> String {
+        self.field_path.join(".")
+    }
+}
+
+/// Three-way merge of secret fields: a field changed on one side only takes that change, nested tables
+/// changed on both sides are merged field by field, and any other field changed on both sides is a conflict.
+/// Conflicting fields keep our value in the merged table until [`resolve_conflict`] is applied.
+pub fn merge_fields(base: &Table, ours: &Table, theirs: &Table) -> (Table, Vec<FieldConflict>) {
+    let mut merged = Table::new();
+    let mut conflicts = Vec::new();
+
+    let keys: BTreeSet<&String> = base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+    for key in keys {
+        let (base_value, our_value, their_value) = (base.get(key), ours.get(key), theirs.get(key));
+
+        let merged_value = if our_value == their_value || their_value == base_value {
+            our_value.cloned()
+        } else if our_value == base_value {
+            their_value.cloned()
+        } else if let (Some(Value::Table(our_table)), Some(Value::Table(their_table))) = (our_value, their_value) {
+            let base_table = match base_value {
+                Some(Value::Table(base_table)) => base_table.clone(),
+                _ => Table::new(),
+            };
+            let (merged_table, nested_conflicts) = merge_fields(&base_table, our_table, their_table);
+            conflicts.extend(nested_conflicts.into_iter().map(|mut conflict| {
+                conflict.field_path.insert(0, key.clone());
+                conflict
+            }));
+            Some(Value::Table(merged_table))
+        } else {
+            conflicts.push(FieldConflict {
+                field_path: vec![key.clone()],
+                ours: our_value.cloned(),
+                theirs: their_value.cloned(),
+            });
+            our_value.cloned()
+        };
+
+        if let Some(merged_value) = merged_value {
+            merged.insert(key.clone(), merged_value);
+        }
+    }
+    (merged, conflicts)
+}
+
+/// Sets the conflicting field of `merged` to the chosen value, removing it when that side deleted it.
+pub fn resolve_conflict(merged: &mut Table, field_path: &[String], value: Option<&Value>) {
+    let Some((key, parent_path)) = field_path.split_last() else {
+        return;
+    };
+
+    let mut table = merged;
+    for parent_key in parent_path {
+        let entry = table.entry(parent_key.clone()).or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        let Value::Table(nested_table) = entry else {
+            return;
+        };
+        table = nested_table;
+    }
+
+    match value {
+        Some(value) => {
+            table.insert(key.clone(), value.clone());
+        }
+        None => {
+            table.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> Table {
+        content.parse().unwrap()
+    }
+
+    #[test]
+    fn take_changes_made_on_one_side() {
+        let base = table("password = \"old\"\nusername = \"alice\"\n");
+        let ours = table("password = \"ours\"\nusername = \"alice\"\n");
+        let theirs = table("password = \"old\"\nusername = \"bob\"\nurl = \"https://example.com\"\n");
+
+        let (merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(merged, table("password = \"ours\"\nusername = \"bob\"\nurl = \"https://example.com\"\n"));
+    }
+
+    #[test]
+    fn take_deletions_made_on_one_side() {
+        let base = table("password = \"old\"\npin = 1234\n");
+        let ours = table("password = \"old\"\n");
+        let theirs = table("password = \"old\"\npin = 1234\n");
+
+        let (merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(merged, table("password = \"old\"\n"));
+    }
+
+    #[test]
+    fn same_change_on_both_sides_is_no_conflict() {
+        let base = table("password = \"old\"\n");
+        let ours = table("password = \"new\"\nport = 22\n");
+        let theirs = table("password = \"new\"\nport = 22\n");
+
+        let (merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(merged, ours);
+    }
+
+    #[test]
+    fn different_changes_on_both_sides_conflict_and_keep_ours() {
+        let base = table("password = \"old\"\nusername = \"alice\"\n");
+        let ours = table("password = \"ours\"\nusername = \"alice\"\n");
+        let theirs = table("password = \"theirs\"\nusername = \"alice\"\n");
+
+        let (merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field_name(), "password");
+        assert_eq!(conflicts[0].ours, Some(Value::from("ours")));
+        assert_eq!(conflicts[0].theirs, Some(Value::from("theirs")));
+        assert_eq!(merged, ours);
+    }
+
+    #[test]
+    fn fields_added_differently_on_both_sides_conflict() {
+        let base = Table::new();
+        let ours = table("pin = 1111\n");
+        let theirs = table("pin = 2222\n");
+
+        let (_, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].ours, Some(Value::from(1111)));
+        assert_eq!(conflicts[0].theirs, Some(Value::from(2222)));
+    }
+
+    #[test]
+    fn change_against_deletion_conflicts() {
+        let base = table("password = \"old\"\npin = 1234\n");
+        let ours = table("password = \"old\"\npin = 4321\n");
+        let theirs = table("password = \"old\"\n");
+
+        let (merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field_name(), "pin");
+        assert_eq!(conflicts[0].ours, Some(Value::from(4321)));
+        assert_eq!(conflicts[0].theirs, None);
+        assert_eq!(merged, ours);
+
+        let (merged, conflicts) = merge_fields(&base, &theirs, &ours);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].ours, None);
+        assert_eq!(conflicts[0].theirs, Some(Value::from(4321)));
+        assert!(!merged.contains_key("pin"));
+    }
+
+    #[test]
+    fn merge_nested_tables_field_by_field() {
+        let base = table("[replica]\nhost = \"old.example\"\nport = 5432\n");
+        let ours = table("[replica]\nhost = \"ours.example\"\nport = 5432\n");
+        let theirs = table("[replica]\nhost = \"old.example\"\nport = 5433\nssl = true\n");
+
+        let (merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(merged, table("[replica]\nhost = \"ours.example\"\nport = 5433\nssl = true\n"));
+    }
+
+    #[test]
+    fn nested_conflicts_carry_their_full_path() {
+        let base = table("[servers.replica]\nport = 5432\nhost = \"db\"\n");
+        let ours = table("[servers.replica]\nport = 6000\nhost = \"db\"\n");
+        let theirs = table("[servers.replica]\nport = 7000\nhost = \"db2\"\n");
+
+        let (mut merged, conflicts) = merge_fields(&base, &ours, &theirs);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field_name(), "servers.replica.port");
+        assert_eq!(merged["servers"]["replica"]["host"].as_str(), Some("db2"));
+
+        resolve_conflict(&mut merged, &conflicts[0].field_path, conflicts[0].theirs.as_ref());
+
+        assert_eq!(merged, table("[servers.replica]\nport = 7000\nhost = \"db2\"\n"));
+    }
+
+    #[test]
+    fn resolve_conflict_with_a_deletion_removes_the_field() {
+        let mut merged = table("password = \"old\"\npin = 4321\n");
+
+        resolve_conflict(&mut merged, &["pin".to_string()], None);
+
+        assert_eq!(merged, table("password = \"old\"\n"));
+    }
+}
@@ -0,0 +1,401 @@
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::Table;
+
+use crate::secret::{Secret, SecretFormat};
+use crate::secret_store::{
+    renamed_from, undelete_message, validate_attachment_name, validate_secret_name, DeletedSecret, SecretRevision, SecretStore,
+};
+
+/// Keeps secrets in memory only, for tests and demos without a GPG keyring.
+#[derive(Default)]
+pub struct MemorySecretStore {
+    secrets: RefCell<BTreeMap<String, Secret>>,
+    /// Attachment content keyed by secret name and attachment name.
+    attachments: RefCell<BTreeMap<(String, String), Vec<u8>>>,
+    revisions: RefCell<Vec<SecretRevision>>,
+    /// Content of each secret right after a revision, keyed by revision id. Deletions have none.
+    snapshots: RefCell<BTreeMap<String, Secret>>,
+}
+
+impl MemorySecretStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store pre-filled with a few sample secrets, used by the `memory` storage backend.
+    pub fn with_demo_secrets() -> Self {
+        let store = Self::new();
+        let demo_secrets = [
+            ("bank", "username = \"username\"\npassword = \"hiddensecret\"\ntotpurl = \"otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example\"\n"),
+            ("social", "username = \"social_user\"\npassword = \"social_password\"\n"),
+            ("work/email", "username = \"me@work.example\"\npassword = \"work_password\"\n"),
+            ("work/database", "username = \"admin\"\npassword = \"db_password\"\nport = 5432\nssl = true\n\n[replica]\nhost = \"replica.work.example\"\nport = 5433\n"),
+        ];
+
+        for (secret_name, content) in demo_secrets {
+            let fields: Table = content.parse().expect("Demo secrets must be valid TOML");
+            store
+                .update_secret(None, secret_name, &Secret::new(fields, SecretFormat::Toml))
+                .expect("Demo secrets must be valid");
+        }
+        store
+    }
+
+    /// Moves every change recorded so far `seconds` into the past, so tests can age the history.
+    #[cfg(test)]
+    pub fn backdate(&self, seconds: i64) {
+        self.revisions.borrow_mut().iter_mut().for_each(|revision| revision.time -= seconds);
+    }
+
+    /// Moves the attachments of `secret_name` to `new_name`, or drops them when `new_name` is `None`.
+    fn move_attachments(&self, secret_name: &str, new_name: Option<&str>) {
+        let mut attachments = self.attachments.borrow_mut();
+        let attachment_keys: Vec<(String, String)> = attachments.keys().filter(|(name, _)| name == secret_name).cloned().collect();
+
+        for attachment_key in attachment_keys {
+            let content = attachments.remove(&attachment_key).unwrap_or_default();
+            if let Some(new_name) = new_name {
+                attachments.insert((new_name.to_string(), attachment_key.1), content);
+            }
+        }
+    }
+
+    fn record(&self, secret_name: &str, message: String) {
+        let mut revisions = self.revisions.borrow_mut();
+        let revision = SecretRevision {
+            id: revisions.len().to_string(),
+            secret_name: secret_name.to_string(),
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as i64).unwrap_or_default(),
+            message,
+            author: None,
+            commit_signature: None,
+        };
+        if let Some(secret) = self.secrets.borrow().get(secret_name) {
+            self.snapshots.borrow_mut().insert(revision.id.clone(), secret.clone());
+        }
+        revisions.push(revision);
+    }
+}
+
+impl SecretStore for MemorySecretStore {
+    fn load_secret_names(&self) -> Result<Vec<String>> {
+        Ok(self.secrets.borrow().keys().cloned().collect())
+    }
+
+    fn load_secrets(&self, secret_name: &str) -> Result<Secret> {
+        self.secrets
+            .borrow()
+            .get(secret_name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Secret '{}' not found.", secret_name))
+    }
+
+    fn update_secret(&self, original_name: Option<&str>, new_name: &str, secret: &Secret) -> Result<()> {
+        let is_renaming = original_name.is_some_and(|original_name| original_name != new_name);
+
+        validate_secret_name(new_name)?;
+        if (is_renaming || original_name.is_none()) && self.secrets.borrow().contains_key(new_name) {
+            return Err(anyhow!("A secret with the name '{}' already exists.", new_name));
+        }
+
+        let mut secrets = self.secrets.borrow_mut();
+        secrets.insert(new_name.to_string(), secret.clone());
+        let message = match original_name {
+            None => format!("Create secret: {}", new_name),
+            Some(original_name) if is_renaming => {
+                secrets.remove(original_name);
+                self.move_attachments(original_name, Some(new_name));
+                format!("Rename secret from {} to {}", original_name, new_name)
+            }
+            Some(_) => format!("Update secret: {}", new_name),
+        };
+        drop(secrets);
+
+        self.record(new_name, message);
+        Ok(())
+    }
+
+    fn delete_secret(&self, secret_name: &str) -> Result<()> {
+        self.secrets
+            .borrow_mut()
+            .remove(secret_name)
+            .ok_or_else(|| anyhow!("Secret '{}' not found.", secret_name))?;
+        self.move_attachments(secret_name, None);
+        self.record(secret_name, format!("Delete secret: {}", secret_name));
+        Ok(())
+    }
+
+    fn history(&self, secret_name: &str) -> Result<Vec<SecretRevision>> {
+        let mut current_name = secret_name.to_string();
+        let mut history = Vec::new();
+
+        for revision in self.revisions.borrow().iter().rev() {
+            if revision.secret_name != current_name {
+                continue;
+            }
+            history.push(revision.clone());
+            if let Some(previous_name) = renamed_from(&revision.message, &current_name) {
+                current_name = previous_name.to_string();
+            }
+        }
+        Ok(history)
+    }
+
+    fn load_secret_revision(&self, revision: &SecretRevision) -> Result<Secret> {
+        self.snapshots
+            .borrow()
+            .get(&revision.id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Secret '{}' does not exist in this version, it was deleted.", revision.secret_name))
+    }
+
+    fn restore_secret(&self, secret_name: &str, revision: &SecretRevision) -> Result<()> {
+        let secret = self.load_secret_revision(revision)?;
+        self.secrets.borrow_mut().insert(secret_name.to_string(), secret);
+        self.record(secret_name, format!("Restore secret: {} to {}", secret_name, revision.id));
+        Ok(())
+    }
+
+    fn deleted_secrets(&self) -> Result<Vec<DeletedSecret>> {
+        let revisions = self.revisions.borrow();
+        let snapshots = self.snapshots.borrow();
+        let mut deleted_secrets: Vec<DeletedSecret> = Vec::new();
+
+        for (index, deletion) in revisions.iter().enumerate().rev() {
+            let secret_name = &deletion.secret_name;
+            if deletion.message != format!("Delete secret: {}", secret_name)
+                || self.secrets.borrow().contains_key(secret_name)
+                || deleted_secrets.iter().any(|deleted_secret| &deleted_secret.secret_name == secret_name)
+            {
+                continue;
+            }
+
+            let last_revision = revisions[..index]
+                .iter()
+                .rev()
+                .find(|revision| &revision.secret_name == secret_name && snapshots.contains_key(&revision.id));
+            if let Some(last_revision) = last_revision {
+                deleted_secrets.push(DeletedSecret {
+                    secret_name: secret_name.clone(),
+                    deletion: deletion.clone(),
+                    last_revision: last_revision.clone(),
+                });
+            }
+        }
+        Ok(deleted_secrets)
+    }
+
+    /// Attachments are dropped on delete, so only the secret itself comes back.
+    fn undelete_secret(&self, deleted_secret: &DeletedSecret, new_name: &str) -> Result<()> {
+        validate_secret_name(new_name)?;
+        if self.secrets.borrow().contains_key(new_name) {
+            return Err(anyhow!("A secret with the name '{}' already exists.", new_name));
+        }
+
+        let secret = self.load_secret_revision(&deleted_secret.last_revision)?;
+        self.secrets.borrow_mut().insert(new_name.to_string(), secret);
+        self.record(new_name, undelete_message(&deleted_secret.secret_name, new_name));
+        Ok(())
+    }
+
+    fn list_attachments(&self, secret_name: &str) -> Result<Vec<String>> {
+        Ok(self
+            .attachments
+            .borrow()
+            .keys()
+            .filter(|(name, _)| name == secret_name)
+            .map(|(_, attachment_name)| attachment_name.clone())
+            .collect())
+    }
+
+    fn load_attachment(&self, secret_name: &str, attachment_name: &str) -> Result<Vec<u8>> {
+        self.attachments
+            .borrow()
+            .get(&(secret_name.to_string(), attachment_name.to_string()))
+            .cloned()
+            .ok_or_else(|| anyhow!("Attachment '{}' not found on secret '{}'.", attachment_name, secret_name))
+    }
+
+    fn add_attachment(&self, secret_name: &str, attachment_name: &str, content: &[u8]) -> Result<()> {
+        validate_attachment_name(attachment_name)?;
+        if !self.secrets.borrow().contains_key(secret_name) {
+            return Err(anyhow!("Secret '{}' does not exist.", secret_name));
+        }
+
+        let attachment_key = (secret_name.to_string(), attachment_name.to_string());
+        if self.attachments.borrow().contains_key(&attachment_key) {
+            return Err(anyhow!("An attachment named '{}' already exists on secret '{}'.", attachment_name, secret_name));
+        }
+
+        self.attachments.borrow_mut().insert(attachment_key, content.to_vec());
+        self.record(secret_name, format!("Attach {} to secret: {}", attachment_name, secret_name));
+        Ok(())
+    }
+
+    fn remove_attachment(&self, secret_name: &str, attachment_name: &str) -> Result<()> {
+        self.attachments
+            .borrow_mut()
+            .remove(&(secret_name.to_string(), attachment_name.to_string()))
+            .ok_or_else(|| anyhow!("Attachment '{}' not found on secret '{}'.", attachment_name, secret_name))?;
+        self.record(secret_name, format!("Remove attachment {} from secret: {}", attachment_name, secret_name));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(password: &str) -> Secret {
+        let mut fields = Table::new();
+        fields.insert("password".to_string(), password.into());
+        Secret::new(fields, SecretFormat::Toml)
+    }
+
+    fn password_of(store: &MemorySecretStore, secret_name: &str) -> String {
+        store.load_secrets(secret_name).unwrap().fields["password"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn create_and_update_secret() {
+        let store = MemorySecretStore::new();
+
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.update_secret(Some("bank"), "bank", &secret("second")).unwrap();
+
+        assert_eq!(store.load_secret_names().unwrap(), vec!["bank".to_string()]);
+        assert_eq!(password_of(&store, "bank"), "second");
+    }
+
+    #[test]
+    fn create_rejects_existing_and_invalid_names() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+
+        assert!(store.update_secret(None, "bank", &secret("second")).is_err());
+        assert!(store.update_secret(None, "work/.hidden", &secret("second")).is_err());
+        assert!(store.update_secret(None, "", &secret("second")).is_err());
+        assert_eq!(password_of(&store, "bank"), "first");
+    }
+
+    #[test]
+    fn rename_moves_secret_and_attachments() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.add_attachment("bank", "card.png", b"image").unwrap();
+
+        store.update_secret(Some("bank"), "finance/bank", &secret("first")).unwrap();
+
+        assert_eq!(store.load_secret_names().unwrap(), vec!["finance/bank".to_string()]);
+        assert!(store.load_secrets("bank").is_err());
+        assert_eq!(store.list_attachments("finance/bank").unwrap(), vec!["card.png".to_string()]);
+        assert!(store.list_attachments("bank").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rename_rejects_existing_name() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.update_secret(None, "social", &secret("second")).unwrap();
+
+        assert!(store.update_secret(Some("bank"), "social", &secret("first")).is_err());
+        assert_eq!(password_of(&store, "bank"), "first");
+        assert_eq!(password_of(&store, "social"), "second");
+    }
+
+    #[test]
+    fn delete_secret_and_its_attachments() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.add_attachment("bank", "card.png", b"image").unwrap();
+
+        store.delete_secret("bank").unwrap();
+
+        assert!(store.load_secret_names().unwrap().is_empty());
+        assert!(store.list_attachments("bank").unwrap().is_empty());
+        assert!(store.delete_secret("bank").is_err());
+    }
+
+    #[test]
+    fn history_follows_renames() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.update_secret(Some("bank"), "bank", &secret("second")).unwrap();
+        store.update_secret(Some("bank"), "finance/bank", &secret("third")).unwrap();
+        store.update_secret(None, "social", &secret("other")).unwrap();
+
+        let history = store.history("finance/bank").unwrap();
+        let messages: Vec<&str> = history.iter().map(|revision| revision.message.as_str()).collect();
+
+        assert_eq!(messages, vec!["Rename secret from bank to finance/bank", "Update secret: bank", "Create secret: bank"]);
+        assert_eq!(store.load_secret_revision(&history[2]).unwrap().fields["password"].as_str(), Some("first"));
+    }
+
+    #[test]
+    fn restore_secret_to_an_earlier_revision() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.update_secret(Some("bank"), "bank", &secret("second")).unwrap();
+        let first_revision = store.history("bank").unwrap().pop().unwrap();
+
+        store.restore_secret("bank", &first_revision).unwrap();
+
+        assert_eq!(password_of(&store, "bank"), "first");
+        assert_eq!(store.history("bank").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn undelete_restores_the_last_revision() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.update_secret(Some("bank"), "bank", &secret("second")).unwrap();
+        store.delete_secret("bank").unwrap();
+
+        let deleted_secrets = store.deleted_secrets().unwrap();
+        assert_eq!(deleted_secrets.len(), 1);
+        assert_eq!(deleted_secrets[0].secret_name, "bank");
+        assert!(store.load_secret_revision(&deleted_secrets[0].deletion).is_err());
+
+        store.undelete_secret(&deleted_secrets[0], "bank").unwrap();
+
+        assert_eq!(password_of(&store, "bank"), "second");
+        assert!(store.deleted_secrets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn undelete_under_a_new_name() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+        store.delete_secret("bank").unwrap();
+        store.update_secret(None, "other", &secret("taken")).unwrap();
+        let deleted_secret = store.deleted_secrets().unwrap().remove(0);
+
+        assert!(store.undelete_secret(&deleted_secret, "other").is_err());
+        store.undelete_secret(&deleted_secret, "old/bank").unwrap();
+
+        assert_eq!(password_of(&store, "old/bank"), "first");
+        assert_eq!(store.history("old/bank").unwrap()[0].message, "Undelete secret: bank as old/bank");
+    }
+
+    #[test]
+    fn add_load_and_remove_attachments() {
+        let store = MemorySecretStore::new();
+        store.update_secret(None, "bank", &secret("first")).unwrap();
+
+        store.add_attachment("bank", "card.png", b"image").unwrap();
+
+        assert_eq!(store.list_attachments("bank").unwrap(), vec!["card.png".to_string()]);
+        assert_eq!(store.load_attachment("bank", "card.png").unwrap(), b"image");
+        assert!(store.add_attachment("bank", "card.png", b"other").is_err());
+        assert!(store.add_attachment("missing", "card.png", b"image").is_err());
+
+        store.remove_attachment("bank", "card.png").unwrap();
+
+        assert!(store.list_attachments("bank").unwrap().is_empty());
+        assert!(store.load_attachment("bank", "card.png").is_err());
+        assert!(store.remove_attachment("bank", "card.png").is_err());
+    }
+}
@@ -0,0 +1,246 @@
+mod memory_store;
+
+use anyhow::{anyhow, Result};
+
+use crate::secret::{Secret, SignatureStatus};
+
+pub use memory_store::MemorySecretStore;
+
+/// Suffix of the folder holding a secret's attachments, e.g. `bank.attachments/` next to `bank.gpg`.
+pub const ATTACHMENTS_SUFFIX: &str = ".attachments";
+
+/// A recorded change of a single secret, newest first when returned by [`SecretStore::history`].
+#[derive(Clone, Debug)]
+pub struct SecretRevision {
+    pub id: String,
+    /// Name of the secret in this revision, which differs from the current one before a rename.
+    pub secret_name: String,
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    pub message: String,
+    /// Who made the change, when the backend records it.
+    pub author: Option<String>,
+    /// Signature check of the change itself, when the backend signs changes.
+    pub commit_signature: Option<SignatureStatus>,
+}
+
+/// A secret that no longer exists in the store but can still be recovered from its history.
+#[derive(Clone, Debug)]
+pub struct DeletedSecret {
+    pub secret_name: String,
+    /// The change that removed the secret.
+    pub deletion: SecretRevision,
+    /// The last revision still containing the secret, readable with [`SecretStore::load_secret_revision`].
+    pub last_revision: SecretRevision,
+}
+
+/// A secret encrypted again to its current recipients, not yet written to the store.
+pub struct ReencryptedSecret {
+    pub secret_name: String,
+    pub ciphertext: Vec<u8>,
+    /// Attachment names with their re-encrypted content.
+    pub attachments: Vec<(String, Vec<u8>)>,
+    pub recipient_fingerprints: Vec<String>,
+}
+
+/// Outcome of the last synchronization with a remote copy of the store.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncStatus {
+    /// No remote is configured.
+    Disabled,
+    /// A remote is configured but has not been synchronized with yet.
+    NotSynced,
+    UpToDate,
+    /// The last change is being pushed to the remote in the background.
+    Pushing,
+    /// Both sides changed the same fields, the merge waits for [`SecretStore::resolve_merge`].
+    Conflicted { conflicts: usize },
+    Failed(String),
+}
+
+/// A change both sides of a diverged sync made differently, resolved by keeping one side.
+#[derive(Clone, Debug)]
+pub struct MergeConflict {
+    /// Secret name, or the path of an attachment or other file within the store.
+    pub name: String,
+    /// Dotted field path, or `None` when only the whole file can be kept from one side.
+    pub field: Option<String>,
+    /// `None` when that side deleted it.
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+/// Storage backend the UI reads and writes secrets through.
+pub trait SecretStore {
+    fn load_secret_names(&self) -> Result<Vec<String>>;
+
+    fn load_secrets(&self, secret_name: &str) -> Result<Secret>;
+
+    /// Creates, updates or renames a secret: `original_name` is `None` when creating.
+    fn update_secret(&self, original_name: Option<&str>, new_name: &str, secret: &Secret) -> Result<()>;
+
+    fn delete_secret(&self, secret_name: &str) -> Result<()>;
+
+    /// Changes of a secret, newest first, following it through renames.
+    fn history(&self, secret_name: &str) -> Result<Vec<SecretRevision>>;
+
+    /// Decrypts a secret as it was in one of its revisions.
+    fn load_secret_revision(&self, revision: &SecretRevision) -> Result<Secret>;
+
+    /// Makes the content of a past revision the current version of `secret_name`, recorded as a new change.
+    fn restore_secret(&self, secret_name: &str, revision: &SecretRevision) -> Result<()>;
+
+    /// Secrets that were deleted and not created again since, most recently deleted first.
+    fn deleted_secrets(&self) -> Result<Vec<DeletedSecret>>;
+
+    /// Brings a deleted secret back with its last content as `new_name`, which may differ from its original name.
+    fn undelete_secret(&self, deleted_secret: &DeletedSecret, new_name: &str) -> Result<()>;
+
+    fn list_attachments(&self, secret_name: &str) -> Result<Vec<String>>;
+
+    /// Returns the decrypted content of an attachment.
+    fn load_attachment(&self, secret_name: &str, attachment_name: &str) -> Result<Vec<u8>>;
+
+    fn add_attachment(&self, secret_name: &str, attachment_name: &str, content: &[u8]) -> Result<()>;
+
+    fn remove_attachment(&self, secret_name: &str, attachment_name: &str) -> Result<()>;
+
+    /// Whether loaded secrets carry a [`SignatureStatus`]. The signature is part of the encrypted file, so it is
+    /// only known once a secret is decrypted.
+    fn signs_secrets(&self) -> bool {
+        false
+    }
+
+    /// Encryption formats secrets can be stored in, e.g. `gpg` and `age`, when the backend supports several.
+    fn encryption_formats(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The one of [`SecretStore::encryption_formats`] a secret is currently stored in.
+    fn encryption_format(&self, _secret_name: &str) -> Result<String> {
+        Err(anyhow!("Encryption formats are not supported by this storage backend."))
+    }
+
+    /// Re-encrypts a secret and its attachments into another encryption format as a single change.
+    fn convert_secret(&self, _secret_name: &str, _format: &str) -> Result<()> {
+        Err(anyhow!("Encryption formats are not supported by this storage backend."))
+    }
+
+    /// Starts noticing changes made to the store by other programs, such as `gpg`, `git pull` or a file sync.
+    /// `on_change` is called from another thread whenever something changed. Stores nothing else can change ignore this.
+    fn watch_external_changes(&self, _on_change: Box<dyn Fn() + Send>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Secrets changed on disk since the last call, or `None` when nothing changed. Any change may have added or
+    /// removed secrets, so the secret names must be reloaded even when no existing secret is listed.
+    fn take_external_changes(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Files in the store that are neither secrets nor attachments, e.g. a plaintext file dropped next to the
+    /// secrets. They are never committed.
+    fn unexpected_files(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Why some secrets cannot be decrypted with the configured keys, e.g. `.age` secrets without an age identity.
+    /// Actions going through every secret are unavailable until then.
+    fn missing_keys(&self) -> Option<String> {
+        None
+    }
+
+    /// Description of the change [`SecretStore::undo`] would revert, so it can be confirmed first.
+    fn undoable_change(&self) -> Result<String> {
+        Err(anyhow!("Undo is not supported by this storage backend."))
+    }
+
+    /// Reverts the most recent change that was not undone yet as a new change, returning its description.
+    /// Changes the store did not make itself, such as merges, are refused.
+    fn undo(&self) -> Result<String> {
+        Err(anyhow!("Undo is not supported by this storage backend."))
+    }
+
+    /// Brings the store and its remote copy up to date with each other.
+    fn sync(&self) -> Result<SyncStatus> {
+        Err(anyhow!("Synchronization is not supported by this storage backend."))
+    }
+
+    fn sync_status(&self) -> SyncStatus {
+        SyncStatus::Disabled
+    }
+
+    /// Conflicts of the last sync, one [`MergeSide`] per conflict is expected by [`SecretStore::resolve_merge`].
+    fn merge_conflicts(&self) -> Vec<MergeConflict> {
+        Vec::new()
+    }
+
+    fn resolve_merge(&self, _resolutions: &[MergeSide]) -> Result<()> {
+        Err(anyhow!("Merging is not supported by this storage backend."))
+    }
+
+    /// Whether secrets can only be read or written after [`SecretStore::unlock`].
+    fn is_locked(&self) -> bool {
+        false
+    }
+
+    /// Unlocks the store with a passphrase for the rest of the session.
+    fn unlock(&self, _passphrase: &str) -> Result<()> {
+        Err(anyhow!("Unlocking is not supported by this storage backend."))
+    }
+
+    /// Decrypts a secret and encrypts it to the currently configured recipients without touching the store.
+    fn reencrypt_secret(&self, _secret_name: &str) -> Result<ReencryptedSecret> {
+        Err(anyhow!("Key rotation is not supported by this storage backend."))
+    }
+
+    /// Replaces all given secrets with their re-encrypted versions as a single change, or none of them on failure.
+    fn apply_reencryption(&self, _reencrypted_secrets: &[ReencryptedSecret]) -> Result<()> {
+        Err(anyhow!("Key rotation is not supported by this storage backend."))
+    }
+}
+
+/// Commit message recording that a deleted secret was brought back, possibly under another name.
+pub fn undelete_message(secret_name: &str, new_name: &str) -> String {
+    if secret_name == new_name {
+        format!("Undelete secret: {}", secret_name)
+    } else {
+        format!("Undelete secret: {} as {}", secret_name, new_name)
+    }
+}
+
+/// Returns the previous name if `message` records renaming a secret to `secret_name`.
+pub fn renamed_from<'a>(message: &'a str, secret_name: &str) -> Option<&'a str> {
+    message.strip_prefix("Rename secret from ")?.strip_suffix(&format!(" to {}", secret_name))
+}
+
+pub fn validate_secret_name(secret_name: &str) -> Result<()> {
+    let is_valid = !secret_name.contains('\\')
+        && secret_name
+            .split('/')
+            .all(|segment| !segment.trim().is_empty() && !segment.starts_with('.') && !segment.ends_with(ATTACHMENTS_SUFFIX));
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid secret name '{}': use slash-separated folders like 'work/aws/prod', without empty or hidden parts or parts ending with '{}'.",
+            secret_name,
+            ATTACHMENTS_SUFFIX
+        ))
+    }
+}
+
+pub fn validate_attachment_name(attachment_name: &str) -> Result<()> {
+    if attachment_name.trim().is_empty() || attachment_name.starts_with('.') || attachment_name.contains(['/', '\\']) {
+        Err(anyhow!("Invalid attachment name '{}': use a plain file name that does not start with a dot.", attachment_name))
+    } else {
+        Ok(())
+    }
+}
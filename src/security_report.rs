@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use toml::{Table, Value};
+
+use crate::modify_secret::ModifySecretUI;
+use crate::password_strength::{PasswordFieldNames, StrengthEstimate};
+use crate::secret_history::format_time;
+use crate::secret_store::SecretStore;
+use eframe::egui::{Align, Button, CentralPanel, Id, Layout, Panel, ProgressBar, RichText, ScrollArea, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const SECURITY_REPORT_TITLE: &str = "Security Report";
+const CLOSE_BUTTON_LABEL: &str = "\u{274c} Close";
+const RUN_BUTTON_LABEL: &str = "\u{1f50d} Run Again";
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+pub const DEFAULT_PASSWORD_MAX_AGE_DAYS: u64 = 365;
+/// Keys that make a secret with a password a login, which should have a `totpurl` for two-factor authentication.
+const LOGIN_KEYS: [&str; 4] = ["username", "login", "user", "email"];
+const TOTP_KEY: &str = "totpurl";
+
+/// A password field of a secret, as `secret / field` in the report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FieldReference {
+    secret_name: String,
+    field_path: String,
+}
+
+struct OldPassword {
+    field: FieldReference,
+    /// Seconds since the Unix epoch of the oldest commit that already had the current password.
+    unchanged_since: i64,
+}
+
+/// Decrypts every secret and lists reused, weak and old passwords and logins without two-factor authentication.
+pub struct SecurityReportUI {
+    secret_store: Rc<dyn SecretStore>,
+    password_field_names: PasswordFieldNames,
+    password_max_age_days: u64,
+    open_dialog: bool,
+    is_running: bool,
+    pending_secrets: Vec<String>,
+    total_secrets: usize,
+    /// Random key of the current scan, so the password hashes can't be looked up or compared across scans.
+    password_hash_key: [u8; 32],
+    /// Fields by the HMAC-SHA256 of their password, so the passwords themselves are not kept while scanning.
+    fields_by_password_hash: HashMap<[u8; 32], Vec<FieldReference>>,
+    reused_passwords: Vec<Vec<FieldReference>>,
+    weak_passwords: Vec<(FieldReference, &'static str)>,
+    old_passwords: Vec<OldPassword>,
+    missing_two_factor: Vec<String>,
+    failures: Vec<(String, String)>,
+    error_message: Option<String>,
+}
+
+impl SecurityReportUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            password_field_names: PasswordFieldNames::default(),
+            password_max_age_days: DEFAULT_PASSWORD_MAX_AGE_DAYS,
+            open_dialog: false,
+            is_running: false,
+            pending_secrets: Vec::new(),
+            total_secrets: 0,
+            password_hash_key: [0; 32],
+            fields_by_password_hash: HashMap::new(),
+            reused_passwords: Vec::new(),
+            weak_passwords: Vec::new(),
+            old_passwords: Vec::new(),
+            missing_two_factor: Vec::new(),
+            failures: Vec::new(),
+            error_message: None,
+        }
+    }
+
+    /// Keys of the fields whose values are checked.
+    pub fn with_password_field_names(mut self, password_field_names: PasswordFieldNames) -> Self {
+        self.password_field_names = password_field_names;
+        self
+    }
+
+    /// Passwords unchanged for longer are reported as old.
+    pub fn with_password_max_age_days(mut self, password_max_age_days: u64) -> Self {
+        self.password_max_age_days = password_max_age_days;
+        self
+    }
+
+    pub fn open(&mut self) {
+        self.start();
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.reset();
+        self.open_dialog = false;
+    }
+
+    fn reset(&mut self) {
+        self.is_running = false;
+        self.pending_secrets = Vec::new();
+        self.total_secrets = 0;
+        self.password_hash_key = [0; 32];
+        self.fields_by_password_hash = HashMap::new();
+        self.reused_passwords = Vec::new();
+        self.weak_passwords = Vec::new();
+        self.old_passwords = Vec::new();
+        self.missing_two_factor = Vec::new();
+        self.failures = Vec::new();
+        self.error_message = None;
+    }
+
+    fn start(&mut self) {
+        self.reset();
+        if let Err(e) = getrandom::fill(&mut self.password_hash_key) {
+            self.error_message = Some(format!("Failed to create a key for comparing passwords: {}", e));
+            return;
+        }
+        match self.secret_store.load_secret_names() {
+            Ok(mut secret_names) => {
+                secret_names.reverse();
+                self.total_secrets = secret_names.len();
+                self.pending_secrets = secret_names;
+                self.is_running = true;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to list secrets: {}", e));
+            }
+        }
+    }
+
+    /// Checks one secret per frame so the progress bar keeps moving, then groups the reused passwords.
+    fn check_next_secret(&mut self, ui: &Ui) {
+        let Some(secret_name) = self.pending_secrets.pop() else {
+            self.finish();
+            return;
+        };
+
+        if let Err(e) = self.check_secret(&secret_name) {
+            log::error!("Failed to check secret '{}' for the security report: {:#?}", secret_name, e);
+            self.failures.push((secret_name, format!("{:#}", e)));
+        }
+        ui.ctx().request_repaint();
+    }
+
+    fn check_secret(&mut self, secret_name: &str) -> Result<()> {
+        let secret = self.secret_store.load_secrets(secret_name)?;
+        let to_reference = |field_path: &str| FieldReference {
+            secret_name: secret_name.to_string(),
+            field_path: field_path.to_string(),
+        };
+
+        let mut password_hashes = Vec::new();
+        for (field_path, password) in self.password_field_names.password_fields(&secret.fields) {
+            let estimate = StrengthEstimate::of(&password);
+            if estimate.is_weak() {
+                self.weak_passwords.push((to_reference(&field_path), estimate.label()));
+            }
+
+            let password_hash = self.password_hash(&password);
+            self.fields_by_password_hash.entry(password_hash).or_default().push(to_reference(&field_path));
+            password_hashes.push((field_path, password_hash));
+        }
+
+        let is_login = !password_hashes.is_empty() && LOGIN_KEYS.iter().any(|key| Self::contains_key(&secret.fields, key));
+        if is_login && !Self::contains_key(&secret.fields, TOTP_KEY) {
+            self.missing_two_factor.push(secret_name.to_string());
+        }
+
+        let cutoff = Self::now() - self.password_max_age_days as i64 * SECONDS_PER_DAY;
+        for (field_path, unchanged_since) in self.unchanged_since(secret_name, &password_hashes, cutoff)? {
+            if unchanged_since < cutoff {
+                self.old_passwords.push(OldPassword {
+                    field: to_reference(&field_path),
+                    unchanged_since,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// When each password got its current value, judged by the history of the secret: the oldest revision of the
+    /// newest run of revisions with that value. Revisions are only decrypted until every password is known to be
+    /// older than `cutoff` or to have changed since, and fields without history are left out. Passwords are
+    /// compared by their hashes, like reused passwords.
+    fn unchanged_since(&self, secret_name: &str, password_hashes: &[(String, [u8; 32])], cutoff: i64) -> Result<Vec<(String, i64)>> {
+        if password_hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut unchanged_since: Vec<Option<i64>> = vec![None; password_hashes.len()];
+        let mut is_settled = vec![false; password_hashes.len()];
+        for revision in self.secret_store.history(secret_name)? {
+            if is_settled.iter().all(|settled| *settled) {
+                break;
+            }
+            let revision_hashes: Vec<(String, [u8; 32])> = self
+                .password_field_names
+                .password_fields(&self.secret_store.load_secret_revision(&revision)?.fields)
+                .into_iter()
+                .map(|(field_path, password)| (field_path, self.password_hash(&password)))
+                .collect();
+
+            for (index, (field_path, password_hash)) in password_hashes.iter().enumerate() {
+                if is_settled[index] {
+                    continue;
+                }
+                let is_same_password = revision_hashes.iter().any(|(revision_path, revision_hash)| revision_path == field_path && revision_hash == password_hash);
+                if is_same_password {
+                    unchanged_since[index] = Some(revision.time);
+                    is_settled[index] = revision.time < cutoff;
+                } else {
+                    is_settled[index] = true;
+                }
+            }
+        }
+
+        Ok(password_hashes
+            .iter()
+            .zip(unchanged_since)
+            .filter_map(|((field_path, _), unchanged_since)| unchanged_since.map(|time| (field_path.clone(), time)))
+            .collect())
+    }
+
+    fn finish(&mut self) {
+        self.is_running = false;
+        self.reused_passwords = self
+            .fields_by_password_hash
+            .drain()
+            .map(|(_, fields)| fields)
+            .filter(|fields| fields.iter().any(|field| field.secret_name != fields[0].secret_name))
+            .collect();
+        self.reused_passwords.sort_by(|first, second| first[0].secret_name.cmp(&second[0].secret_name));
+    }
+
+    fn password_hash(&self, password: &str) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.password_hash_key).expect("HMAC accepts keys of any length");
+        mac.update(password.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    fn contains_key(fields: &Table, key: &str) -> bool {
+        fields.iter().any(|(field_key, value)| field_key == key || matches!(value, Value::Table(table) if Self::contains_key(table, key)))
+    }
+
+    fn now() -> i64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64)
+    }
+
+    /// Button that opens the secret in the modify dialog, so the finding can be fixed right away.
+    fn show_secret_link(ui: &mut Ui, label: &str, secret_name: &str, modify_secret_ui: &mut ModifySecretUI) {
+        if ui.link(label).on_hover_text("Modify this secret").clicked() {
+            modify_secret_ui.open(secret_name);
+        }
+    }
+
+    fn show_section_heading(ui: &mut Ui, title: &str, count: usize) {
+        ui.add_space(6.0);
+        let heading = if count == 0 { format!("\u{2714} {}: none", title) } else { format!("\u{26a0} {}: {}", title, count) };
+        ui.label(RichText::new(heading).strong());
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, modify_secret_ui: &mut ModifySecretUI) {
+        if self.open_dialog {
+            let security_report_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(560.0, 480.0))
+                .with_title(SECURITY_REPORT_TITLE)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("security_report_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, security_report_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                if self.is_running {
+                    self.check_next_secret(ui);
+                }
+
+                Panel::bottom(Id::new("security_report_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CLOSE_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.add_enabled(!self.is_running, Button::new(RUN_BUTTON_LABEL)).clicked() {
+                                self.start();
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                        return;
+                    }
+                    if self.is_running {
+                        let checked_secrets = self.total_secrets - self.pending_secrets.len();
+                        ui.add(ProgressBar::new(checked_secrets as f32 / self.total_secrets.max(1) as f32).text(format!("{} / {}", checked_secrets, self.total_secrets)));
+                        return;
+                    }
+
+                    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+                        self.show_findings(ui, modify_secret_ui);
+                    });
+                });
+            });
+        }
+    }
+
+    fn show_findings(&self, ui: &mut Ui, modify_secret_ui: &mut ModifySecretUI) {
+        ui.label(format!("Checked {} secret(s). Click a secret to modify it.", self.total_secrets));
+
+        Self::show_section_heading(ui, "Reused passwords", self.reused_passwords.len());
+        self.reused_passwords.iter().for_each(|fields| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Same password in");
+                fields.iter().for_each(|field| {
+                    Self::show_secret_link(ui, &format!("{} / {}", field.secret_name, field.field_path), &field.secret_name, modify_secret_ui);
+                });
+            });
+        });
+
+        Self::show_section_heading(ui, "Weak passwords", self.weak_passwords.len());
+        self.weak_passwords.iter().for_each(|(field, label)| {
+            ui.horizontal(|ui| {
+                Self::show_secret_link(ui, &format!("{} / {}", field.secret_name, field.field_path), &field.secret_name, modify_secret_ui);
+                ui.label(*label);
+            });
+        });
+
+        Self::show_section_heading(ui, &format!("Passwords older than {} days", self.password_max_age_days), self.old_passwords.len());
+        self.old_passwords.iter().for_each(|old_password| {
+            ui.horizontal(|ui| {
+                let field = &old_password.field;
+                Self::show_secret_link(ui, &format!("{} / {}", field.secret_name, field.field_path), &field.secret_name, modify_secret_ui);
+                let age_days = (Self::now() - old_password.unchanged_since) / SECONDS_PER_DAY;
+                ui.label(format!("unchanged since {} ({} days)", format_time(old_password.unchanged_since), age_days));
+            });
+        });
+
+        Self::show_section_heading(ui, "Logins without two-factor authentication", self.missing_two_factor.len());
+        self.missing_two_factor.iter().for_each(|secret_name| {
+            ui.horizontal(|ui| {
+                Self::show_secret_link(ui, secret_name, secret_name, modify_secret_ui);
+                ui.label(format!("has no {} field", TOTP_KEY));
+            });
+        });
+
+        if !self.failures.is_empty() {
+            Self::show_section_heading(ui, "Secrets that could not be checked", self.failures.len());
+            self.failures.iter().for_each(|(secret_name, error)| {
+                ui.colored_label(ui.style().visuals.error_fg_color, format!("{}: {}", secret_name, error));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::{Secret, SecretFormat};
+    use crate::secret_store::MemorySecretStore;
+
+    fn save(store: &MemorySecretStore, secret_name: &str, content: &str) {
+        let original_name = store.load_secrets(secret_name).ok().map(|_| secret_name);
+        store.update_secret(original_name, secret_name, &Secret::new(content.parse().unwrap(), SecretFormat::Toml)).unwrap();
+    }
+
+    /// Runs a whole scan without the dialog, one secret after the other like the frames do.
+    fn scan(store: MemorySecretStore) -> SecurityReportUI {
+        let secret_store: Rc<dyn SecretStore> = Rc::new(store);
+        let mut security_report_ui = SecurityReportUI::new(&secret_store);
+        security_report_ui.start();
+        while let Some(secret_name) = security_report_ui.pending_secrets.pop() {
+            security_report_ui.check_secret(&secret_name).unwrap();
+        }
+        security_report_ui.finish();
+        security_report_ui
+    }
+
+    fn reference(secret_name: &str, field_path: &str) -> FieldReference {
+        FieldReference {
+            secret_name: secret_name.to_string(),
+            field_path: field_path.to_string(),
+        }
+    }
+
+    #[test]
+    fn reused_passwords_are_grouped_across_secrets_only() {
+        let store = MemorySecretStore::new();
+        save(&store, "bank", "password = \"shared-Pa55word\"\n");
+        save(&store, "mail", "[account]\npassword = \"shared-Pa55word\"\n");
+        save(&store, "shop", "password = \"unique-Pa55word\"\n");
+        save(&store, "wifi", "password = \"own-Pa55word\"\npin = \"own-Pa55word\"\n");
+
+        let security_report_ui = scan(store);
+
+        assert_eq!(security_report_ui.reused_passwords, vec![vec![reference("bank", "password"), reference("mail", "account.password")]]);
+        assert!(security_report_ui.fields_by_password_hash.is_empty());
+    }
+
+    #[test]
+    fn password_hashes_are_keyed_per_scan() {
+        let store = MemorySecretStore::new();
+        save(&store, "bank", "password = \"s3cret\"\n");
+        let secret_store: Rc<dyn SecretStore> = Rc::new(store);
+        let mut security_report_ui = SecurityReportUI::new(&secret_store);
+
+        security_report_ui.start();
+        let first_hash = security_report_ui.password_hash("s3cret");
+        assert_eq!(security_report_ui.password_hash("s3cret"), first_hash);
+        assert_ne!(security_report_ui.password_hash("s3cret!"), first_hash);
+
+        security_report_ui.start();
+        assert_ne!(security_report_ui.password_hash("s3cret"), first_hash);
+    }
+
+    #[test]
+    fn login_and_totp_keys_are_found_in_nested_tables() {
+        let store = MemorySecretStore::new();
+        save(&store, "nested-login", "[site]\nusername = \"alice\"\npassword = \"s3cret\"\n");
+        save(&store, "nested-totp", "username = \"alice\"\npassword = \"s3cret\"\n[otp]\ntotpurl = \"otpauth://totp/x?secret=JBSWY3DPEHPK3PXP\"\n");
+        save(&store, "no-login", "password = \"s3cret\"\n");
+        save(&store, "no-password", "username = \"alice\"\n");
+
+        let security_report_ui = scan(store);
+
+        assert_eq!(security_report_ui.missing_two_factor, vec!["nested-login".to_string()]);
+    }
+
+    #[test]
+    fn contains_key_searches_every_level() {
+        let fields: Table = "user = \"alice\"\n[a.b.c]\ntotpurl = \"otpauth://\"\n".parse().unwrap();
+
+        assert!(SecurityReportUI::contains_key(&fields, "user"));
+        assert!(SecurityReportUI::contains_key(&fields, "totpurl"));
+        assert!(SecurityReportUI::contains_key(&fields, "c"));
+        assert!(!SecurityReportUI::contains_key(&fields, "email"));
+    }
+
+    #[test]
+    fn old_passwords_are_dated_by_their_first_revision() {
+        let age = (DEFAULT_PASSWORD_MAX_AGE_DAYS as i64 + 30) * SECONDS_PER_DAY;
+        let store = MemorySecretStore::new();
+        save(&store, "old", "username = \"alice\"\npassword = \"old-Pa55word\"\n");
+        save(&store, "changed", "password = \"first-Pa55word\"\n");
+        store.backdate(age);
+        save(&store, "old", "username = \"bob\"\npassword = \"old-Pa55word\"\n");
+        save(&store, "changed", "password = \"second-Pa55word\"\n");
+        save(&store, "new", "password = \"new-Pa55word\"\n");
+        let oldest_time = store.history("old").unwrap().last().unwrap().time;
+
+        let security_report_ui = scan(store);
+
+        assert_eq!(security_report_ui.old_passwords.len(), 1);
+        assert_eq!(security_report_ui.old_passwords[0].field, reference("old", "password"));
+        assert_eq!(security_report_ui.old_passwords[0].unchanged_since, oldest_time);
+    }
+
+    #[test]
+    fn unchanged_since_stops_at_the_last_change_of_each_field() {
+        let store = MemorySecretStore::new();
+        save(&store, "bank", "password = \"first\"\npin = \"1234\"\n");
+        store.backdate(3000);
+        save(&store, "bank", "password = \"second\"\npin = \"1234\"\n");
+        store.backdate(2000);
+        save(&store, "bank", "password = \"second\"\npin = \"1234\"\nnote = \"edited\"\n");
+        let times: Vec<i64> = store.history("bank").unwrap().iter().map(|revision| revision.time).collect();
+        let secret_store: Rc<dyn SecretStore> = Rc::new(store);
+        let mut security_report_ui = SecurityReportUI::new(&secret_store);
+        security_report_ui.start();
+
+        let password_hashes = vec![
+            ("password".to_string(), security_report_ui.password_hash("second")),
+            ("pin".to_string(), security_report_ui.password_hash("1234")),
+            ("passphrase".to_string(), security_report_ui.password_hash("never saved")),
+        ];
+        let unchanged_since = security_report_ui.unchanged_since("bank", &password_hashes, i64::MIN).unwrap();
+
+        assert_eq!(unchanged_since, vec![("password".to_string(), times[1]), ("pin".to_string(), times[2])]);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::password_strength::StrengthEstimate;
+use eframe::egui::{Color32, Id, ProgressBar, RichText, Ui};
+
+const METER_WIDTH: f32 = 160.0;
+
+fn score_color(estimate: &StrengthEstimate) -> Color32 {
+    match estimate.score {
+        0 => Color32::from_rgb(200, 60, 60),
+        1 => Color32::from_rgb(220, 120, 40),
+        2 => Color32::from_rgb(200, 170, 30),
+        3 => Color32::from_rgb(120, 170, 60),
+        _ => Color32::from_rgb(60, 160, 60),
+    }
+}
+
+fn crack_times_text(estimate: &StrengthEstimate) -> String {
+    estimate.crack_times().iter().map(|(scenario, time)| format!("{}: {}", scenario, time)).collect::<Vec<String>>().join("\n")
+}
+
+/// Rates `password` once per value instead of every frame. The estimate is kept in the temporary memory under
+/// `id.with("cache")` together with a hash of the value it was made for, and recomputed when the text changes.
+pub fn cached_strength_estimate(ui: &Ui, id: Id, password: &str) -> StrengthEstimate {
+    let cache_id = id.with("cache");
+    let value_hash = Id::new(password);
+    if let Some((cached_hash, estimate)) = ui.data(|reader| reader.get_temp::<(Id, StrengthEstimate)>(cache_id))
+        && cached_hash == value_hash
+    {
+        return estimate;
+    }
+
+    let estimate = StrengthEstimate::of(password);
+    ui.data_mut(|writer| writer.insert_temp(cache_id, (value_hash, estimate.clone())));
+    estimate
+}
+
+/// Colored meter with the offline crack time and feedback, shown below a password while it is typed.
+pub fn show_strength_meter(ui: &mut Ui, estimate: &StrengthEstimate) {
+    let offline_crack_time = estimate.crack_times().last().map(|(_, time)| time.clone()).unwrap_or_default();
+    ui.horizontal(|ui| {
+        ui.add(
+            ProgressBar::new(f32::from(estimate.score + 1) / 5.0)
+                .desired_width(METER_WIDTH)
+                .fill(score_color(estimate))
+                .text(estimate.label()),
+        )
+        .on_hover_text(crack_times_text(estimate));
+        ui.label(RichText::new(format!("cracked offline in {}", offline_crack_time)).small());
+    });
+    if let Some(warning) = estimate.warning {
+        ui.label(RichText::new(warning).small().color(score_color(estimate)));
+    }
+    estimate.suggestions.iter().for_each(|suggestion| {
+        ui.label(RichText::new(*suggestion).small().weak());
+    });
+}
+
+/// Small colored dot with the rating, the details are in its tooltip.
+pub fn show_strength_indicator(ui: &mut Ui, estimate: &StrengthEstimate) {
+    let tooltip = match estimate.warning {
+        Some(warning) => format!("Strength: {}\n{}\n\n{}", estimate.label(), warning, crack_times_text(estimate)),
+        None => format!("Strength: {}\n\n{}", estimate.label(), crack_times_text(estimate)),
+    };
+    ui.label(RichText::new("\u{25cf}").small().color(score_color(estimate))).on_hover_text(tooltip);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::{Context, RawInput};
+
+    #[test]
+    fn cached_estimate_follows_the_value() {
+        let context = Context::default();
+        let id = Id::new("password_field");
+
+        let _ = context.run_ui(RawInput::default(), |ui| {
+            assert_eq!(cached_strength_estimate(ui, id, "password"), StrengthEstimate::of("password"));
+            assert_eq!(cached_strength_estimate(ui, id, "password"), StrengthEstimate::of("password"));
+            assert_eq!(cached_strength_estimate(ui, id, "correct horse battery staple"), StrengthEstimate::of("correct horse battery staple"));
+
+            let cached = ui.data(|reader| reader.get_temp::<(Id, StrengthEstimate)>(id.with("cache")));
+            assert_eq!(cached, Some((Id::new("correct horse battery staple"), StrengthEstimate::of("correct horse battery staple"))));
+        });
+    }
+}
@@ -0,0 +1,118 @@
+use std::rc::Rc;
+
+use crate::cache::forget_secret;
+use crate::secret_store::SecretStore;
+use eframe::egui::{Align, CentralPanel, Id, Layout, Panel, RichText, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const UNDO_CHANGE_TITLE: &str = "Undo Change";
+const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
+const UNDO_BUTTON_LABEL: &str = "\u{21b6} Undo";
+
+/// Asks before reverting the last change, and shows what was undone, or why not, in the bottom bar.
+pub struct UndoChangeUI {
+    secret_store: Rc<dyn SecretStore>,
+    /// Description of the change the dialog asks to undo.
+    change: String,
+    open_dialog: bool,
+    /// Description of the last undone change, or why undo failed.
+    undo_result: Option<Result<String, String>>,
+}
+
+impl UndoChangeUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            change: "".to_string(),
+            open_dialog: false,
+            undo_result: None,
+        }
+    }
+
+    /// Asks to confirm the change undo would revert, or reports right away why there is none.
+    pub fn open(&mut self) {
+        match self.secret_store.undoable_change() {
+            Ok(change) => {
+                self.change = change;
+                self.open_dialog = true;
+            }
+            Err(e) => {
+                self.undo_result = Some(Err(format!("{:#}", e)));
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.change = "".to_string();
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let undo_change_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(450.0, 125.0))
+                .with_title(UNDO_CHANGE_TITLE)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("undo_change_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, undo_change_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("undo_change_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CANCEL_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.button(UNDO_BUTTON_LABEL).clicked() {
+                                self.handle_undo(ui);
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    ui.label("Revert this change with a new change?");
+                    ui.label(RichText::new(&self.change).strong());
+                });
+            });
+        }
+    }
+
+    /// The outcome of the last undo, shown in the bottom bar.
+    pub fn show_result(&self, ui: &mut Ui) {
+        match &self.undo_result {
+            Some(Ok(undone_change)) => {
+                ui.label(format!("Undone: {}", undone_change));
+            }
+            Some(Err(error)) => {
+                ui.colored_label(ui.style().visuals.error_fg_color, "\u{26a0} undo failed").on_hover_text(error);
+            }
+            None => {}
+        }
+    }
+
+    fn handle_undo(&mut self, ui: &Ui) {
+        self.undo_result = Some(self.secret_store.undo().map_err(|e| {
+            log::error!("Failed to undo the last change: {:#?}", e);
+            format!("{:#}", e)
+        }));
+        Self::clear_ui_cache(ui);
+        self.close();
+    }
+
+    /// Forgets the secret names and every decrypted secret, an undo may have changed any of them.
+    fn clear_ui_cache(ui: &Ui) {
+        let secret_names_cache_id = Id::new("secret_names").with("cache");
+        let secret_names: Vec<String> = ui.data(|reader| reader.get_temp(secret_names_cache_id)).unwrap_or_default();
+
+        ui.memory_mut(|m| {
+            secret_names.iter().for_each(|secret_name| forget_secret(&mut m.data, secret_name));
+            m.data.remove::<Vec<String>>(secret_names_cache_id);
+        });
+    }
+}
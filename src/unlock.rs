@@ -0,0 +1,125 @@
+use std::rc::Rc;
+
+use crate::secret_store::SecretStore;
+use eframe::egui::{Align, CentralPanel, Id, Key, Layout, Panel, TextEdit, Ui, Vec2, ViewportBuilder, ViewportId};
+
+const UNLOCK_TITLE: &str = "Unlock Vault";
+const CANCEL_BUTTON_LABEL: &str = "\u{274c} Cancel";
+const UNLOCK_BUTTON_LABEL: &str = "\u{1f513} Unlock";
+
+pub struct UnlockUI {
+    secret_store: Rc<dyn SecretStore>,
+    passphrase: String,
+    confirmation: String,
+    is_new_vault: bool,
+    open_dialog: bool,
+    error_message: Option<String>,
+}
+
+impl UnlockUI {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            secret_store: Rc::clone(secret_store),
+            passphrase: String::new(),
+            confirmation: String::new(),
+            is_new_vault: false,
+            open_dialog: false,
+            error_message: None,
+        }
+    }
+
+    /// A vault without secrets accepts any passphrase, so it has to be typed twice.
+    pub fn open(&mut self) {
+        self.is_new_vault = self.secret_store.load_secret_names().map(|secret_names| secret_names.is_empty()).unwrap_or(false);
+        self.open_dialog = true;
+    }
+
+    fn close(&mut self) {
+        self.open_dialog = false;
+        self.passphrase = String::new();
+        self.confirmation = String::new();
+        self.error_message = None;
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        if self.open_dialog {
+            let unlock_dialog = ViewportBuilder::default()
+                .with_inner_size(Vec2::new(420.0, if self.is_new_vault { 190.0 } else { 140.0 }))
+                .with_title(UNLOCK_TITLE)
+                .with_close_button(true)
+                .with_decorations(true);
+            let dialog_id = ViewportId::from_hash_of("unlock_dialog");
+
+            ui.ctx().show_viewport_immediate(dialog_id, unlock_dialog, |ui, _| {
+                if ui.ctx().input(|input_state| input_state.viewport().close_requested()) {
+                    self.close();
+                }
+
+                Panel::bottom(Id::new("unlock_bottom_panel")).show_inside(ui, |ui| {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button(CANCEL_BUTTON_LABEL).clicked() {
+                                self.close();
+                            }
+                            if ui.button(UNLOCK_BUTTON_LABEL).clicked() {
+                                self.handle_unlock(ui);
+                            }
+                        });
+                    });
+                    ui.add_space(2.0);
+                });
+
+                CentralPanel::default().show_inside(ui, |ui| {
+                    if self.is_new_vault {
+                        ui.label("The vault is empty, choose a passphrase for it:");
+                    } else {
+                        ui.label("Enter the passphrase of the vault:");
+                    }
+                    let passphrase_field = ui.add(TextEdit::singleline(&mut self.passphrase).password(true));
+                    if !passphrase_field.has_focus() && self.passphrase.is_empty() {
+                        passphrase_field.request_focus();
+                    }
+                    if self.is_new_vault {
+                        ui.label("Repeat the passphrase:");
+                        ui.add(TextEdit::singleline(&mut self.confirmation).password(true));
+                    }
+                    if let Some(error) = &self.error_message {
+                        ui.colored_label(ui.style().visuals.error_fg_color, error);
+                    }
+                });
+
+                if ui.input(|i| i.key_pressed(Key::Enter)) {
+                    self.handle_unlock(ui);
+                }
+            });
+        }
+    }
+
+    fn handle_unlock(&mut self, ui: &mut Ui) {
+        if self.passphrase.is_empty() {
+            self.error_message = Some("The passphrase cannot be empty.".to_string());
+            return;
+        }
+        if self.is_new_vault && self.passphrase != self.confirmation {
+            self.error_message = Some("The passphrases do not match.".to_string());
+            return;
+        }
+
+        match self.secret_store.unlock(&self.passphrase) {
+            Ok(_) => {
+                Self::clear_ui_cache(ui);
+                self.close();
+            }
+            Err(e) => {
+                self.passphrase = String::new();
+                self.error_message = Some(format!("Unable to unlock the vault: {}", e));
+            }
+        }
+    }
+
+    fn clear_ui_cache(ui: &mut Ui) {
+        let secret_names_cache_id = Id::new("secret_names").with("cache");
+        ui.memory_mut(|m| m.data.remove::<Vec<String>>(secret_names_cache_id));
+    }
+}
@@ -1,41 +1,140 @@
 mod secret_section;
+mod secret_tree;
 
 use std::rc::Rc;
+use std::time::Duration;
 
-use crate::credentials_provider::CredentialsProvider;
+use crate::cache::forget_secret;
+use crate::secret_store::{SecretStore, SyncStatus};
+use crate::attachment::AttachmentUI;
+use crate::breach_check::BreachCheckUI;
+use crate::breached_passwords::BreachedPasswords;
+use crate::convert_secret::ConvertSecretUI;
 use crate::delete_secret::DeleteSecretUI;
+use crate::deleted_secrets::DeletedSecretsUI;
 use crate::modify_secret::ModifySecretUI;
+use crate::passphrase_generator::Wordlist;
+use crate::password_generator::PasswordPolicy;
+use crate::password_strength::PasswordFieldNames;
+use crate::merge_conflicts::MergeConflictsUI;
+use crate::rotate_keys::RotateKeysUI;
+use crate::secret_history::SecretHistoryUI;
+use crate::security_report::SecurityReportUI;
+use crate::undo_change::UndoChangeUI;
+use crate::unlock::UnlockUI;
 use crate::view_secret::secret_section::SecretSectionUI;
+use crate::view_secret::secret_tree::SecretFolder;
 use eframe::{App, Frame};
 use eframe::egui::{
-    Align, CentralPanel, FontFamily, FontId, Id, Key, Layout, Panel, TextEdit,
+    Align, Button, CentralPanel, CollapsingHeader, Color32, FontFamily, FontId, Id, Key, Layout, Panel, RichText, TextEdit,
     TextStyle, Ui, Vec2, ViewportBuilder, ViewportCommand,
 };
 
+/// Dialogs opened from the menu of a secret.
+pub struct SecretDialogs {
+    pub modify_secret_ui: ModifySecretUI,
+    pub delete_secret_ui: DeleteSecretUI,
+    pub attachment_ui: AttachmentUI,
+    pub convert_secret_ui: ConvertSecretUI,
+    pub secret_history_ui: SecretHistoryUI,
+    pub breach_check_ui: BreachCheckUI,
+}
+
+impl SecretDialogs {
+    fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
+        Self {
+            modify_secret_ui: ModifySecretUI::new(secret_store),
+            delete_secret_ui: DeleteSecretUI::new(secret_store),
+            attachment_ui: AttachmentUI::new(secret_store),
+            convert_secret_ui: ConvertSecretUI::new(secret_store),
+            secret_history_ui: SecretHistoryUI::new(secret_store),
+            breach_check_ui: BreachCheckUI::new(secret_store),
+        }
+    }
+
+    fn show(&mut self, ui: &mut Ui) {
+        self.modify_secret_ui.show(ui);
+        self.delete_secret_ui.show(ui);
+        self.attachment_ui.show(ui);
+        self.convert_secret_ui.show(ui);
+        self.secret_history_ui.show(ui);
+        self.breach_check_ui.show(ui);
+    }
+}
+
 pub struct ViewSecretUI {
-    credentials_provider: Rc<CredentialsProvider>,
+    secret_store: Rc<dyn SecretStore>,
     search_field: Id,
     search_term: String,
     initial_search_focus: bool,
-    modify_secret_ui: ModifySecretUI,
-    delete_secret_ui: DeleteSecretUI,
+    secret_dialogs: SecretDialogs,
+    rotate_keys_ui: RotateKeysUI,
+    deleted_secrets_ui: DeletedSecretsUI,
+    security_report_ui: SecurityReportUI,
+    merge_conflicts_ui: MergeConflictsUI,
+    unlock_ui: UnlockUI,
     secret_section_ui: SecretSectionUI,
+    /// Plaintext or unknown files in the store, checked whenever the secret names are reloaded.
+    unexpected_files: Vec<String>,
+    /// Why some secrets cannot be decrypted, checked whenever the secret names are reloaded.
+    missing_keys: Option<String>,
+    undo_change_ui: UndoChangeUI,
 }
 
 impl ViewSecretUI {
-    pub fn new(credentials_provider: &Rc<CredentialsProvider>, _version: String) -> Self {
+    pub fn new(secret_store: &Rc<dyn SecretStore>, _version: String) -> Self {
         Self {
-            credentials_provider: Rc::clone(credentials_provider),
+            secret_store: Rc::clone(secret_store),
             search_field: Id::new("search_field"),
             search_term: String::new(),
             initial_search_focus: false,
-            modify_secret_ui: ModifySecretUI::new(credentials_provider),
-            delete_secret_ui: DeleteSecretUI::new(credentials_provider),
-            secret_section_ui: SecretSectionUI::new(credentials_provider),
+            secret_dialogs: SecretDialogs::new(secret_store),
+            rotate_keys_ui: RotateKeysUI::new(secret_store),
+            deleted_secrets_ui: DeletedSecretsUI::new(secret_store),
+            security_report_ui: SecurityReportUI::new(secret_store),
+            merge_conflicts_ui: MergeConflictsUI::new(secret_store),
+            unlock_ui: UnlockUI::new(secret_store),
+            secret_section_ui: SecretSectionUI::new(secret_store),
+            unexpected_files: Vec::new(),
+            missing_keys: None,
+            undo_change_ui: UndoChangeUI::new(secret_store),
         }
     }
 
-    pub fn run(self, version: String) -> eframe::Result {
+    /// Named policies and wordlists offered by the password generator when adding or modifying a secret.
+    pub fn with_password_generator(mut self, password_policies: Vec<PasswordPolicy>, wordlists: Vec<Wordlist>) -> Self {
+        self.secret_dialogs.modify_secret_ui = self.secret_dialogs.modify_secret_ui.with_password_generator(password_policies, wordlists);
+        self
+    }
+
+    /// Keys of the fields whose values are rated for strength, in the viewer and while editing.
+    pub fn with_password_field_names(mut self, password_field_names: PasswordFieldNames) -> Self {
+        self.secret_section_ui = self.secret_section_ui.with_password_field_names(password_field_names.clone());
+        self.secret_dialogs.modify_secret_ui = self.secret_dialogs.modify_secret_ui.with_password_field_names(password_field_names.clone());
+        self.secret_dialogs.breach_check_ui = self.secret_dialogs.breach_check_ui.with_password_field_names(password_field_names.clone());
+        self.security_report_ui = self.security_report_ui.with_password_field_names(password_field_names);
+        self
+    }
+
+    /// Passwords unchanged for longer are reported as old by the security report.
+    pub fn with_password_max_age_days(mut self, password_max_age_days: u64) -> Self {
+        self.security_report_ui = self.security_report_ui.with_password_max_age_days(password_max_age_days);
+        self
+    }
+
+    /// Local copy of the breached password hashes that password fields are checked against, the checks are
+    /// hidden without it.
+    pub fn with_breached_passwords(mut self, breached_passwords: Option<BreachedPasswords>) -> Self {
+        self.secret_dialogs.breach_check_ui = self.secret_dialogs.breach_check_ui.with_breached_passwords(breached_passwords);
+        self
+    }
+
+    pub fn run(mut self, version: String) -> eframe::Result {
+        if self.secret_store.is_locked() {
+            self.unlock_ui.open();
+        }
+        self.merge_conflicts_ui.open_if_conflicted();
+
         let title = format!("Rustillium v.{}", version);
         let options = eframe::NativeOptions {
             viewport: ViewportBuilder::default().with_inner_size(Vec2::new(640.0, 480.0)),
@@ -44,7 +143,13 @@ impl ViewSecretUI {
         eframe::run_native(
             &title,
             options,
-            Box::new(|_cc| Ok(Box::new(self))),
+            Box::new(|cc| {
+                let egui_ctx = cc.egui_ctx.clone();
+                if let Err(e) = self.secret_store.watch_external_changes(Box::new(move || egui_ctx.request_repaint())) {
+                    log::error!("Failed to watch the secrets for external changes: {:#?}", e);
+                }
+                Ok(Box::new(self))
+            }),
         )
     }
 
@@ -72,20 +177,162 @@ impl ViewSecretUI {
                      if ui.button("\u{2bab} Exit").clicked() {
                          ViewSecretUI::close(ui.ctx());
                     };
-                    if ui.button("\u{2795} Add Secret").clicked() {
-                        self.modify_secret_ui.open("");
+                    let is_locked = self.secret_store.is_locked();
+                    let unavailable_reason = if is_locked { Some("Unlock the vault first.".to_string()) } else { self.missing_keys.clone() };
+                    let is_available = unavailable_reason.is_none();
+                    let unavailable_reason = unavailable_reason.unwrap_or_default();
+
+                    if ui.add_enabled(!is_locked, Button::new("\u{2795} Add Secret")).on_disabled_hover_text(&unavailable_reason).clicked() {
+                        self.secret_dialogs.modify_secret_ui.open("");
+                    }
+                    if ui.add_enabled(is_available, Button::new("\u{1f511} Rotate Keys")).on_disabled_hover_text(&unavailable_reason).clicked() {
+                        self.rotate_keys_ui.open();
+                    }
+                    if ui.add_enabled(is_available, Button::new("\u{1f5d1} Deleted Secrets")).on_disabled_hover_text(&unavailable_reason).clicked() {
+                        self.deleted_secrets_ui.open();
                     }
+                    if ui
+                        .add_enabled(is_available, Button::new("\u{1f4cb} Security Report"))
+                        .on_hover_text("Find reused, weak and old passwords and logins without 2FA")
+                        .on_disabled_hover_text(&unavailable_reason)
+                        .clicked()
+                    {
+                        self.security_report_ui.open();
+                    }
+                    if self.secret_dialogs.breach_check_ui.is_available()
+                        && ui
+                            .add_enabled(is_available, Button::new("\u{1f6e1} Breach Check"))
+                            .on_hover_text("Check every password against the breached password hashes")
+                            .on_disabled_hover_text(&unavailable_reason)
+                            .clicked()
+                    {
+                        self.secret_dialogs.breach_check_ui.open_for_vault();
+                    }
+                    if ui
+                        .add_enabled(is_available, Button::new("\u{21b6} Undo"))
+                        .on_hover_text("Undo the last change (Ctrl+Z)")
+                        .on_disabled_hover_text(&unavailable_reason)
+                        .clicked()
+                    {
+                        self.undo_change_ui.open();
+                    }
+                    let sync_status = self.secret_store.sync_status();
+                    if sync_status != SyncStatus::Disabled {
+                        if ui.button("\u{1f504} Sync").clicked() {
+                            self.handle_sync(ui);
+                        }
+                        Self::build_sync_status(&sync_status, ui);
+                    }
+                    self.undo_change_ui.show_result(ui);
                 });
             });
             ui.add_space(2.0);
         });
     }
 
+    fn build_sync_status(sync_status: &SyncStatus, ui: &mut Ui) {
+        let (status, color, details) = match sync_status {
+            SyncStatus::Disabled => return,
+            SyncStatus::NotSynced => ("not synced".to_string(), ui.style().visuals.weak_text_color(), "Press Sync to synchronize with the remote.".to_string()),
+            SyncStatus::UpToDate => ("\u{2714} in sync".to_string(), Color32::from_rgb(60, 160, 60), "Up to date with the remote.".to_string()),
+            SyncStatus::Pushing => {
+                // Nothing else repaints once the push is done.
+                ui.ctx().request_repaint_after(Duration::from_millis(250));
+                ("\u{23f3} pushing".to_string(), ui.style().visuals.weak_text_color(), "The last change is being pushed to the remote.".to_string())
+            }
+            SyncStatus::Conflicted { conflicts } => (
+                format!("\u{26a0} {} conflict(s)", conflicts),
+                ui.style().visuals.error_fg_color,
+                "The same secrets were changed here and on the remote. Press Sync to resolve the conflicts.".to_string(),
+            ),
+            SyncStatus::Failed(error) => ("\u{26a0} sync failed".to_string(), ui.style().visuals.error_fg_color, error.clone()),
+        };
+        ui.label(RichText::new(status).color(color)).on_hover_text(details);
+    }
+
+    fn handle_sync(&mut self, ui: &Ui) {
+        if let Err(e) = self.secret_store.sync() {
+            log::error!("Failed to synchronize the secrets: {:#?}", e);
+        }
+        Self::clear_ui_cache(ui);
+        self.merge_conflicts_ui.open_if_conflicted();
+    }
+
+    /// Picks up secrets added, changed or removed by other programs, e.g. `gpg`, `git pull` or a file sync.
+    fn handle_external_changes(&mut self, ui: &mut Ui) {
+        let Some(changed_secrets) = self.secret_store.take_external_changes() else {
+            return;
+        };
+
+        ui.memory_mut(|m| m.data.remove::<Vec<String>>(Id::new("secret_names").with("cache")));
+        changed_secrets.iter().for_each(|secret| self.secret_section_ui.reload_changed_secret(ui, secret));
+    }
+
+    /// Forgets the secret names and every decrypted secret, a sync may have changed any of them.
+    fn clear_ui_cache(ui: &Ui) {
+        let secret_names_cache_id = Id::new("secret_names").with("cache");
+        let secret_names: Vec<String> = ui.data(|reader| reader.get_temp(secret_names_cache_id)).unwrap_or_default();
+
+        ui.memory_mut(|m| {
+            secret_names.iter().for_each(|secret_name| forget_secret(&mut m.data, secret_name));
+            m.data.remove::<Vec<String>>(secret_names_cache_id);
+        });
+    }
+
+    /// Shown instead of the secrets until the passphrase of the vault has been entered.
+    fn build_locked_panel(&mut self, ui: &mut Ui) {
+        CentralPanel::default().show_inside(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(40.0);
+                ui.label("\u{1f512} The vault is locked.");
+                if ui.button("\u{1f513} Unlock").clicked() {
+                    self.unlock_ui.open();
+                }
+            });
+        });
+        self.unlock_ui.show(ui);
+    }
+
+    fn show_folder(&mut self, ui: &mut Ui, folder: &SecretFolder, folder_path: &str, is_searching: bool) {
+        folder.folders.iter().for_each(|(folder_name, subfolder)| {
+            let subfolder_path = format!("{}{}/", folder_path, folder_name);
+            CollapsingHeader::new(format!("\u{1f4c1} {}", folder_name))
+                .id_salt(("secret_folder", &subfolder_path))
+                .open(is_searching.then_some(true))
+                .show(ui, |ui| {
+                    self.show_folder(ui, subfolder, &subfolder_path, is_searching);
+                });
+        });
+
+        folder.secrets.iter().for_each(|secret| {
+            self.secret_section_ui.show(ui, secret, &mut self.secret_dialogs);
+        });
+    }
+
     fn close(ctx: &eframe::egui::Context) {
         ctx.send_viewport_cmd(ViewportCommand::Close);
     }
 
-    fn load_secret_names(&self, ui: &Ui) -> Vec<String> {
+    /// Warns about files that are not committed and may be plaintext secrets someone dropped into the store.
+    fn build_unexpected_files_banner(&self, ui: &mut Ui) {
+        if self.unexpected_files.is_empty() {
+            return;
+        }
+
+        Panel::top(Id::new("unexpected_files_panel")).show_inside(ui, |ui| {
+            ui.add_space(4.0);
+            let warning = format!(
+                "\u{26a0} {} unencrypted or unknown file(s) in the secrets directory, they are not committed: {}",
+                self.unexpected_files.len(),
+                self.unexpected_files.join(", ")
+            );
+            ui.colored_label(ui.style().visuals.error_fg_color, warning)
+                .on_hover_text("Encrypt or remove them, plaintext files next to your secrets can leak them.");
+            ui.add_space(2.0);
+        });
+    }
+
+    fn load_secret_names(&mut self, ui: &Ui) -> Vec<String> {
         let cache_id = Id::new("secret_names").with("cache");
         let cached_secret_names: Option<Vec<String>> = ui.data(|reader| reader.get_temp(cache_id));
 
@@ -93,7 +340,9 @@ impl ViewSecretUI {
         if let Some(secret_names) = cached_secret_names {
             secret_names
         } else {
-            let loaded_secret_names = self.credentials_provider.load_secret_names().expect("Cannot load secret names");
+            let loaded_secret_names = self.secret_store.load_secret_names().expect("Cannot load secret names");
+            self.unexpected_files = self.secret_store.unexpected_files();
+            self.missing_keys = self.secret_store.missing_keys();
             ui.data_mut(|writer| {
                 writer.insert_temp(cache_id, loaded_secret_names.clone());
             });
@@ -109,34 +358,43 @@ impl App for ViewSecretUI {
 
         self.build_bottom_panel(ui);
 
+        if self.secret_store.is_locked() {
+            self.build_locked_panel(ui);
+            if ui.input(|i| i.key_pressed(Key::Q) && i.modifiers.ctrl) {
+                Self::close(ui.ctx());
+            }
+            return;
+        }
+
+        self.handle_external_changes(ui);
         let secrets = self.load_secret_names(ui);
-        let search_term = &mut self.search_term;
+        self.build_unexpected_files_banner(ui);
 
         CentralPanel::default().show_inside(ui, |ui| {
             ui.horizontal(|ui| {
                 ui.label("Search: ");
                 ui.add_sized(
                     ui.available_size(),
-                    TextEdit::singleline(search_term)
+                    TextEdit::singleline(&mut self.search_term)
                         .id(self.search_field)
                         .hint_text("search by secret name"),
                 );
             });
             eframe::egui::ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
-                let search_lower = search_term.to_lowercase();
-                secrets
-                    .iter()
-                    .filter(|secret| secret.to_lowercase().contains(&search_lower))
-                    .for_each(|secret| {
-                        self.secret_section_ui
-                            .show(ui, secret, &mut self.modify_secret_ui, &mut self.delete_secret_ui);
-                    });
+                let search_lower = self.search_term.to_lowercase();
+                let matching_secrets = secrets.iter().filter(|secret| secret.to_lowercase().contains(&search_lower));
+                let root_folder = SecretFolder::from_secret_names(matching_secrets);
+                self.show_folder(ui, &root_folder, "", !search_lower.is_empty());
             });
         });
 
-        // Show modify/delete dialog viewports
-        self.modify_secret_ui.show(ui);
-        self.delete_secret_ui.show(ui);
+        // Show modify/delete/attachment/convert/history/breach/rotate/trash/report/merge/undo dialog viewports
+        self.secret_dialogs.show(ui);
+        self.rotate_keys_ui.show(ui);
+        self.deleted_secrets_ui.show(ui);
+        self.security_report_ui.show(ui, &mut self.secret_dialogs.modify_secret_ui);
+        self.merge_conflicts_ui.show(ui);
+        self.undo_change_ui.show(ui);
 
         // Keyboard shortcuts
         if ui.input(|i| i.key_pressed(Key::F) && i.modifiers.ctrl) {
@@ -145,6 +403,10 @@ impl App for ViewSecretUI {
         if ui.input(|i| i.key_pressed(Key::Q) && i.modifiers.ctrl) {
             Self::close(ui.ctx());
         }
+        // While the search field has text, Ctrl+Z undoes typing in it instead.
+        if self.search_term.is_empty() && self.missing_keys.is_none() && ui.input(|i| i.key_pressed(Key::Z) && i.modifiers.ctrl) {
+            self.undo_change_ui.open();
+        }
 
         self.secret_section_ui.handle_popup(ui.ctx());
     }
@@ -1,17 +1,27 @@
-use crate::cache::CachedSecretsResult;
-use crate::credentials_provider::CredentialsProvider;
-use crate::delete_secret::DeleteSecretUI;
-use crate::modify_secret::ModifySecretUI;
+use crate::attachment::AttachmentUI;
+use crate::view_secret::SecretDialogs;
+use crate::cache::{CachedSecret, CachedSecretsResult, signature_id};
+use crate::password_strength::PasswordFieldNames;
+use crate::secret::SignatureStatus;
+use crate::secret_store::SecretStore;
+use crate::strength_meter::{cached_strength_estimate, show_strength_indicator};
 use crate::totp_provider::generate_totp_display_info;
-use eframe::egui::{Align, Button, Context, Id, Layout, Popup, PopupCloseBehavior, RectAlign, Ui, Widget, collapsing_header};
+use eframe::egui::{Align, Button, Color32, Context, Id, Layout, Popup, PopupCloseBehavior, RectAlign, RichText, Ui, Widget, collapsing_header};
 use log;
-use std::collections::HashMap;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
+use toml::{Table, Value};
+
+/// Values longer than this, or spanning several lines, are collapsed until expanded.
+const LONG_VALUE_LENGTH: usize = 80;
+const COLLAPSED_VALUE_LENGTH: usize = 60;
+/// How long an expanded secret shows that it changed on disk.
+const CHANGED_NOTICE_DURATION: Duration = Duration::from_secs(5);
 
 pub struct SecretSectionUI {
-    credentials_provider: Rc<CredentialsProvider>,
+    secret_store: Rc<dyn SecretStore>,
     popup_state: Option<PopupState>,
+    password_field_names: PasswordFieldNames,
 }
 
 struct PopupState {
@@ -20,14 +30,21 @@ struct PopupState {
 }
 
 impl SecretSectionUI {
-    pub fn new(credentials_provider: &Rc<CredentialsProvider>) -> Self {
+    pub fn new(secret_store: &Rc<dyn SecretStore>) -> Self {
         Self {
-            credentials_provider: Rc::clone(credentials_provider),
+            secret_store: Rc::clone(secret_store),
             popup_state: None,
+            password_field_names: PasswordFieldNames::default(),
         }
     }
 
-    pub fn show(&mut self, ui: &mut Ui, secret: &str, modify_secret_ui: &mut ModifySecretUI, delete_secret_ui: &mut DeleteSecretUI) {
+    /// Keys of the fields that get a strength indicator next to their value.
+    pub fn with_password_field_names(mut self, password_field_names: PasswordFieldNames) -> Self {
+        self.password_field_names = password_field_names;
+        self
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, secret: &str, secret_dialogs: &mut SecretDialogs) {
         let collapsible_state = collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), Id::new(secret), false);
         let is_collapsible_open = collapsible_state.is_open();
 
@@ -36,17 +53,20 @@ impl SecretSectionUI {
             ui.memory_mut(|m| m.data.remove::<CachedSecretsResult>(cache_id));
         }
 
+        let signature = self.signature_status(ui, secret);
         collapsible_state
             .show_header(ui, |ui| {
-                Self::build_header(secret, is_collapsible_open, modify_secret_ui, delete_secret_ui, ui);
+                Self::build_header(secret, is_collapsible_open, signature.as_ref(), secret_dialogs, ui);
             })
             .body(|ui| {
                 if is_collapsible_open {
+                    Self::build_changed_notice(secret, ui);
                     match self.load_secrets(ui, secret) {
                         Ok(secrets) => {
-                            secrets.iter().for_each(|(key, value)| {
-                                self.build_secret_section(key, value, ui);
+                            secrets.fields.iter().for_each(|(key, value)| {
+                                self.build_secret_section(key, key, value, ui);
                             });
+                            Self::build_attachments_section(secret, &secrets.attachments, &mut secret_dialogs.attachment_ui, ui);
                         }
                         Err(_) => {
                             ui.colored_label(ui.style().visuals.error_fg_color, "Error: Could not load secret.");
@@ -56,19 +76,64 @@ impl SecretSectionUI {
             });
     }
 
-    fn build_secret_section(&mut self, key: &String, value: &String, ui: &mut Ui) {
-        if key == "totpurl" {
-            self.build_totp_section(key, value, ui);
+    /// Reloads an expanded secret that changed on disk and flags it when its content differs. The dialogs drop
+    /// the cache of what they change, so Rustillium's own changes are reloaded without a notice.
+    pub fn reload_changed_secret(&self, ui: &mut Ui, secret: &str) {
+        let cache_id = Id::new(secret).with("cache");
+        ui.data_mut(|writer| writer.remove::<Option<SignatureStatus>>(signature_id(secret)));
+        let Some(previous_result) = ui.data(|reader| reader.get_temp::<CachedSecretsResult>(cache_id)) else {
+            return;
+        };
+
+        ui.memory_mut(|m| m.data.remove::<CachedSecretsResult>(cache_id));
+        let is_changed = match (previous_result, self.load_secrets(ui, secret)) {
+            (Ok(previous), Ok(reloaded)) => previous.fields != reloaded.fields || previous.attachments != reloaded.attachments,
+            (Err(_), Err(_)) => false,
+            _ => true,
+        };
+        if is_changed {
+            ui.data_mut(|writer| writer.insert_temp(Id::new(secret).with("changed_on_disk"), Instant::now()));
+        }
+    }
+
+    fn build_changed_notice(secret: &str, ui: &mut Ui) {
+        let notice_id = Id::new(secret).with("changed_on_disk");
+        let Some(changed_at) = ui.data(|reader| reader.get_temp::<Instant>(notice_id)) else {
+            return;
+        };
+
+        if changed_at.elapsed() < CHANGED_NOTICE_DURATION {
+            ui.label(RichText::new("\u{21bb} Changed on disk, showing the new version").small().color(Color32::from_rgb(200, 150, 30)));
+            ui.ctx().request_repaint_after(CHANGED_NOTICE_DURATION - changed_at.elapsed());
         } else {
-            self.build_single_secret_section(key, value, ui);
+            ui.data_mut(|writer| writer.remove::<Instant>(notice_id));
+        }
+    }
+
+    /// `field_path` is the dotted path of the field within the secret, e.g. `database.password`.
+    fn build_secret_section(&mut self, key: &str, field_path: &str, value: &Value, ui: &mut Ui) {
+        match value {
+            Value::String(totp_url) if key == "totpurl" => self.build_totp_section(field_path, totp_url, ui),
+            Value::String(text) => self.build_single_secret_section(key, field_path, text, ui),
+            Value::Table(table) => self.build_table_section(key, field_path, table, ui),
+            other => self.build_single_secret_section(key, field_path, &other.to_string(), ui),
         }
     }
 
-    fn build_totp_section(&mut self, key: &String, value: &str, ui: &mut Ui) {
+    fn build_table_section(&mut self, key: &str, field_path: &str, table: &Table, ui: &mut Ui) {
+        ui.label(RichText::new(key).strong());
+        ui.indent(Id::new(field_path).with("table"), |ui| {
+            table.iter().for_each(|(nested_key, nested_value)| {
+                self.build_secret_section(nested_key, &format!("{}.{}", field_path, nested_key), nested_value, ui);
+            });
+        });
+    }
+
+    fn build_totp_section(&mut self, field_path: &str, value: &str, ui: &mut Ui) {
         ui.horizontal(|ui| {
             ui.label("TOTP code");
             ui.with_layout(Layout::top_down_justified(Align::LEFT), |ui| {
-                let popup_id = Id::new(key);
+                let popup_id = Id::new(field_path);
                 match generate_totp_display_info(value) {
                     Ok(totp) => {
                         let totp_code_as_button = Button::new(&totp.code).fill(ui.ctx().theme().default_visuals().faint_bg_color).ui(ui);
@@ -88,7 +153,7 @@ impl SecretSectionUI {
                         ui.label(format!("{} seconds left", totp.remaining_seconds));
                     }
                     Err(e) => {
-                        log::error!("Failed to generate TOTP for key '{}': {:#?}", key, e);
+                        log::error!("Failed to generate TOTP for key '{}': {:#?}", field_path, e);
                         ui.colored_label(ui.style().visuals.error_fg_color, "Error: Invalid TOTP URL");
                     }
                 }
@@ -96,12 +161,28 @@ impl SecretSectionUI {
         });
     }
 
-    fn build_single_secret_section(&mut self, key: &String, value: &String, ui: &mut Ui) {
+    fn build_single_secret_section(&mut self, key: &str, field_path: &str, value: &str, ui: &mut Ui) {
+        let expanded_id = ui.id().with(field_path).with("expanded");
+        let strength_id = ui.id().with(field_path).with("strength");
+        let is_long_value = value.contains('\n') || value.chars().count() > LONG_VALUE_LENGTH;
+        let is_expanded = ui.data(|reader| reader.get_temp::<bool>(expanded_id)).unwrap_or(false);
+
         ui.horizontal(|ui| {
             ui.label(key);
+            if !value.is_empty() && self.password_field_names.matches(key) {
+                let estimate = cached_strength_estimate(ui, strength_id, value);
+                show_strength_indicator(ui, &estimate);
+            }
+            if is_long_value {
+                let toggle_label = if is_expanded { "\u{23f6}" } else { "\u{23f7}" };
+                if ui.small_button(toggle_label).on_hover_text("Show the whole value").clicked() {
+                    ui.data_mut(|writer| writer.insert_temp(expanded_id, !is_expanded));
+                }
+            }
             ui.with_layout(Layout::top_down_justified(Align::LEFT), |ui| {
-                let popup_id = Id::new(key);
-                let secret_value_as_button = Button::new(value).fill(ui.ctx().theme().default_visuals().faint_bg_color).ui(ui);
+                let popup_id = Id::new(field_path);
+                let displayed_value = if is_long_value && !is_expanded { Self::to_collapsed_value(value) } else { value.to_string() };
+                let secret_value_as_button = Button::new(displayed_value).fill(ui.ctx().theme().default_visuals().faint_bg_color).ui(ui);
 
                 if secret_value_as_button.clicked() {
                     self.copy_secret(value, popup_id, ui);
@@ -117,6 +198,11 @@ impl SecretSectionUI {
         });
     }
 
+    fn to_collapsed_value(value: &str) -> String {
+        let first_line: String = value.lines().next().unwrap_or_default().chars().take(COLLAPSED_VALUE_LENGTH).collect();
+        format!("{}\u{2026}", first_line)
+    }
+
     fn copy_secret(&mut self, value: &str, popup_id: Id, ui: &mut Ui) {
         self.popup_state = Some(PopupState {
             id: popup_id,
@@ -125,21 +211,94 @@ impl SecretSectionUI {
         ui.ctx().copy_text(value.to_owned());
     }
 
-    fn build_header(secret: &str, is_collapsible_open: bool, modify_secret_ui: &mut ModifySecretUI, delete_secret_ui: &mut DeleteSecretUI, ui: &mut Ui) {
-        ui.label(secret);
+    fn build_attachments_section(secret: &str, attachments: &[String], attachment_ui: &mut AttachmentUI, ui: &mut Ui) {
+        if attachments.is_empty() {
+            return;
+        }
+
+        ui.label(RichText::new("Attachments").strong());
+        ui.indent(Id::new(secret).with("attachments"), |ui| {
+            attachments.iter().for_each(|attachment_name| {
+                if ui.button(format!("\u{1f4ce} {}", attachment_name)).on_hover_text("Save or remove this attachment").clicked() {
+                    attachment_ui.open_attachment(secret, attachment_name);
+                }
+            });
+        });
+    }
+
+    fn build_header(secret: &str, is_collapsible_open: bool, signature: Option<&SignatureStatus>, secret_dialogs: &mut SecretDialogs, ui: &mut Ui) {
+        let secret_leaf_name = secret.rsplit('/').next().unwrap_or(secret);
+        ui.label(secret_leaf_name);
+        if let Some(signature) = signature {
+            Self::build_signature_badge(signature, ui);
+        }
         if is_collapsible_open {
             let preferences_button = ui.button("⛭");
             Popup::menu(&preferences_button).align(RectAlign::RIGHT_START).show(|ui| {
                 if ui.button("\u{1f58a} Modify").clicked() {
-                    modify_secret_ui.open(secret);
+                    secret_dialogs.modify_secret_ui.open(secret);
+                };
+                if ui.button("\u{1f4ce} Attach File").clicked() {
+                    secret_dialogs.attachment_ui.open_attach(secret);
+                };
+                if ui.button("\u{1f558} History").clicked() {
+                    secret_dialogs.secret_history_ui.open(secret);
+                };
+                if secret_dialogs.breach_check_ui.is_available() && ui.button("\u{1f6e1} Check Breaches").clicked() {
+                    secret_dialogs.breach_check_ui.open_for_secret(secret);
+                };
+                if secret_dialogs.convert_secret_ui.is_available() && ui.button("\u{1f501} Convert Format").clicked() {
+                    secret_dialogs.convert_secret_ui.open(secret);
                 };
                 if ui.button("\u{1f5d1} Delete").clicked() {
-                    delete_secret_ui.open(secret);
+                    secret_dialogs.delete_secret_ui.open(secret);
                 };
             });
         }
     }
 
+    /// The last signature check of a secret, kept apart from the decrypted secret so it survives collapsing.
+    /// The signature is stored inside the encrypted file, so a secret of a signing store that was not checked
+    /// yet is decrypted once just for its badge, one secret per frame to keep the list responsive.
+    fn signature_status(&self, ui: &mut Ui, secret: &str) -> Option<SignatureStatus> {
+        if !self.secret_store.signs_secrets() {
+            return None;
+        }
+        if let Some(signature) = ui.data(|reader| reader.get_temp::<Option<SignatureStatus>>(signature_id(secret))) {
+            return signature;
+        }
+
+        let check_pass_id = Id::new("signature_check").with("pass");
+        let pass_nr = ui.ctx().cumulative_pass_nr();
+        if ui.data(|reader| reader.get_temp::<u64>(check_pass_id)) == Some(pass_nr) {
+            ui.ctx().request_repaint();
+            return None;
+        }
+        ui.data_mut(|writer| writer.insert_temp(check_pass_id, pass_nr));
+
+        let signature = match self.secret_store.load_secrets(secret) {
+            Ok(loaded_secret) => loaded_secret.signature,
+            Err(e) => {
+                log::error!("Failed to check the signature of secret '{}': {:#?}", secret, e);
+                None
+            }
+        };
+        ui.data_mut(|writer| writer.insert_temp(signature_id(secret), signature.clone()));
+        signature
+    }
+
+    fn build_signature_badge(signature: &SignatureStatus, ui: &mut Ui) {
+        let (badge, color, tooltip) = match signature {
+            SignatureStatus::Valid(signer) => ("\u{2714} signed", Color32::from_rgb(60, 160, 60), format!("Signed by {}", signer)),
+            SignatureStatus::UnknownSigner(fingerprint) => {
+                ("\u{2754} unknown signer", Color32::from_rgb(200, 150, 30), format!("Signed by an untrusted or unknown key {}", fingerprint))
+            }
+            SignatureStatus::Unsigned => ("unsigned", ui.style().visuals.weak_text_color(), "This secret is not signed".to_string()),
+            SignatureStatus::Bad => ("\u{26a0} bad signature", ui.style().visuals.error_fg_color, "The signature does not match, this secret may have been tampered with".to_string()),
+        };
+        ui.label(RichText::new(badge).small().color(color)).on_hover_text(tooltip);
+    }
+
     pub fn handle_popup(&mut self, ctx: &Context) {
         if let Some(popup) = &self.popup_state {
             if popup.opened_at.elapsed() >= Duration::from_secs(1) {
@@ -157,21 +316,37 @@ impl SecretSectionUI {
             return cached_result;
         }
 
-        let result = self.credentials_provider.load_secrets(secret);
+        let result = self.secret_store.load_secrets(secret);
         let final_result_to_cache = match result {
-            Ok(loaded_secrets) => Ok(Self::to_displayed_secrets(loaded_secrets)),
+            Ok(loaded_secret) => Ok(CachedSecret {
+                fields: Self::to_displayed_secrets(loaded_secret.fields),
+                attachments: self.load_attachment_names(secret),
+                signature: loaded_secret.signature,
+            }),
             Err(e) => {
                 log::error!("Failed to load secret '{}': {:#?}", secret, e);
                 Err(format!("Error: {:#?}", e))
             }
         };
 
-        ui.data_mut(|writer| writer.insert_temp(cache_id, final_result_to_cache.clone()));
+        ui.data_mut(|writer| {
+            if let Ok(cached_secret) = &final_result_to_cache {
+                writer.insert_temp(signature_id(secret), cached_secret.signature.clone());
+            }
+            writer.insert_temp(cache_id, final_result_to_cache.clone())
+        });
         final_result_to_cache
     }
 
-    fn to_displayed_secrets(mut secrets: HashMap<String, String>) -> Vec<(String, String)> {
-        let mut result: Vec<(String, String)> = Vec::new();
+    fn load_attachment_names(&self, secret: &str) -> Vec<String> {
+        self.secret_store.list_attachments(secret).unwrap_or_else(|e| {
+            log::error!("Failed to list attachments of secret '{}': {:#?}", secret, e);
+            Vec::new()
+        })
+    }
+
+    fn to_displayed_secrets(mut secrets: Table) -> Vec<(String, Value)> {
+        let mut result: Vec<(String, Value)> = Vec::new();
 
         if let Some(totpurl) = secrets.remove("totpurl") {
             result.push(("totpurl".to_string(), totpurl));
@@ -183,10 +358,16 @@ impl SecretSectionUI {
             result.push(("password".to_string(), password));
         }
 
-        let mut remaining_secrets: Vec<(String, String)> = secrets.into_iter().collect();
+        let notes = secrets.remove("notes");
+
+        let mut remaining_secrets: Vec<(String, Value)> = secrets.into_iter().collect();
         remaining_secrets.sort_by(|first, second| first.0.cmp(&second.0));
 
         result.extend(remaining_secrets);
+        if let Some(notes) = notes {
+            result.push(("notes".to_string(), notes));
+        }
         result
     }
 }
+
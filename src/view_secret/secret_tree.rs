@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+/// Folder view over slash-separated secret names such as `work/aws/prod`.
+#[derive(Default)]
+pub struct SecretFolder {
+    pub folders: BTreeMap<String, SecretFolder>,
+    pub secrets: Vec<String>,
+}
+
+impl SecretFolder {
+    pub fn from_secret_names<'a>(secret_names: impl Iterator<Item = &'a String>) -> Self {
+        let mut root = SecretFolder::default();
+
+        secret_names.for_each(|secret_name| {
+            let mut folder = &mut root;
+            let mut segments: Vec<&str> = secret_name.split('/').collect();
+            segments.pop();
+
+            for segment in segments {
+                folder = folder.folders.entry(segment.to_string()).or_default();
+            }
+            folder.secrets.push(secret_name.clone());
+        });
+
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_folder(secret_names: &[&str]) -> SecretFolder {
+        let secret_names: Vec<String> = secret_names.iter().map(|secret_name| secret_name.to_string()).collect();
+        SecretFolder::from_secret_names(secret_names.iter())
+    }
+
+    #[test]
+    fn nested_names_are_placed_in_their_folders() {
+        let root = to_folder(&["bank", "work/aws/prod", "work/aws/dev", "work/mail"]);
+
+        assert_eq!(root.secrets, vec!["bank"]);
+        assert_eq!(root.folders.keys().collect::<Vec<_>>(), vec!["work"]);
+        let work = &root.folders["work"];
+        assert_eq!(work.secrets, vec!["work/mail"]);
+        assert_eq!(work.folders["aws"].secrets, vec!["work/aws/prod", "work/aws/dev"]);
+        assert!(work.folders["aws"].folders.is_empty());
+    }
+
+    #[test]
+    fn folders_are_sorted_and_secrets_keep_their_order() {
+        let root = to_folder(&["zeta/a", "alpha/b", "mail", "bank", "middle/c"]);
+
+        assert_eq!(root.folders.keys().collect::<Vec<_>>(), vec!["alpha", "middle", "zeta"]);
+        assert_eq!(root.secrets, vec!["mail", "bank"]);
+    }
+
+    #[test]
+    fn a_folder_can_share_its_name_with_a_secret() {
+        let root = to_folder(&["work", "work/aws"]);
+
+        assert_eq!(root.secrets, vec!["work"]);
+        assert_eq!(root.folders["work"].secrets, vec!["work/aws"]);
+    }
+
+    #[test]
+    fn no_names_give_an_empty_folder() {
+        let root = to_folder(&[]);
+
+        assert!(root.secrets.is_empty());
+        assert!(root.folders.is_empty());
+    }
+}